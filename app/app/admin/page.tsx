@@ -30,6 +30,13 @@ import {
 } from "lucide-react";
 import { motion, AnimatePresence } from "framer-motion";
 
+// Mock feeds are keyed by seed; the admin tools use the token mint as the seed.
+const mockFeed = (seed: PublicKey, programId: PublicKey) =>
+    PublicKey.findProgramAddressSync([Buffer.from("mock_oracle"), seed.toBuffer()], programId)[0];
+
+// Oracle source for legacy push feeds; the mock oracle build ignores it.
+const legacyOracle = { kind: { pythLegacy: {} }, feedId: new Array(32).fill(0) };
+
 export default function AdminPage() {
     const { getProgram } = useAnchorProgram();
     const { connection } = useConnection();
@@ -70,9 +77,8 @@ export default function AdminPage() {
     useEffect(() => {
         const program = getProgram();
         if (program) {
-            const [oraclePda] = PublicKey.findProgramAddressSync([Buffer.from("mock_oracle")], program.programId);
-            setOracle(oraclePda.toBase58());
-            setPsmOracle(oraclePda.toBase58());
+            setOracle(mockFeed(new PublicKey(mockCollateral), program.programId).toBase58());
+            setPsmOracle(mockFeed(new PublicKey(mockUsdc), program.programId).toBase58());
             if (!psmMint) setPsmMint(mockUsdc);
             if (!collateralMint) setCollateralMint(mockCollateral);
         }
@@ -163,14 +169,19 @@ export default function AdminPage() {
             const mintPubkey = new PublicKey(collateralMint);
             const oraclePubkey = new PublicKey(oracle);
 
+            const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("collateral"), mintPubkey.toBuffer()], program.programId);
+
             const tx = await program.methods.configureCollateral(
+                mintPubkey,
+                oraclePubkey,
+                legacyOracle,
                 new BN(parseInt(mcr)),
                 new BN(parseInt(ltr)),
                 new BN(parseInt(penalty))
             ).accounts({
-                admin: keypair.publicKey,
-                collateralMint: mintPubkey,
-                oracle: oraclePubkey,
+                collateralConfig: configPda,
+                oracleFeed: oraclePubkey,
+                authority: keypair.publicKey,
             } as any).rpc();
 
             setStatus("Collateral Configured! Tx: " + tx);
@@ -192,16 +203,19 @@ export default function AdminPage() {
             const [globalState] = PublicKey.findProgramAddressSync([Buffer.from("global_state")], program.programId);
             const [psmConfigPda] = PublicKey.findProgramAddressSync([Buffer.from("psm"), mintPubkey.toBuffer()], program.programId);
             const [psmVaultPda] = PublicKey.findProgramAddressSync([Buffer.from("psm_vault"), mintPubkey.toBuffer()], program.programId);
+            const [psmFeeVaultPda] = PublicKey.findProgramAddressSync([Buffer.from("psm_fee_vault"), mintPubkey.toBuffer()], program.programId);
             const [psmAuthorityPda] = PublicKey.findProgramAddressSync([Buffer.from("psm_authority")], program.programId);
 
-            const tx = await program.methods.configurePsm(feeBN)
+            // The same fee applies to both swap directions (tin and tout).
+            const tx = await program.methods.configurePsm(legacyOracle, feeBN, feeBN)
                 .accounts({
-                    admin: keypair.publicKey,
+                    authority: keypair.publicKey,
                     globalState: globalState,
                     tokenMint: mintPubkey,
                     oracle: oraclePubkey,
                     psmConfig: psmConfigPda,
                     psmVault: psmVaultPda,
+                    psmFeeVault: psmFeeVaultPda,
                     psmAuthority: psmAuthorityPda,
                     tokenProgram: TOKEN_PROGRAM_ID,
                     systemProgram: SystemProgram.programId,
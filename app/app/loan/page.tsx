@@ -104,7 +104,8 @@ export default function LoanPage() {
                         globalState: globalState,
                     } as any).rpc();
             } else if (action === "Mint") {
-                tx = await program.methods.mintUsdt(amountBN)
+                // No price bound: mint at whatever price the oracle reports.
+                tx = await program.methods.mintUsdt(amountBN, null)
                     .accounts({
                         owner: wallet.publicKey,
                         position: positionPda,
@@ -120,6 +121,7 @@ export default function LoanPage() {
                         owner: wallet.publicKey,
                         position: positionPda,
                         usdtMint: usdtMint,
+                        collateralConfig: configPda,
                         collateralMint: mintPubkey,
                         globalState: globalState,
                     } as any).rpc();
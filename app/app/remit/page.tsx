@@ -172,13 +172,15 @@ export default function RemitWizard() {
             const [psmVault] = PublicKey.findProgramAddressSync([Buffer.from("psm_vault"), usdcMintPubkey.toBuffer()], program.programId);
             const [psmConfig] = PublicKey.findProgramAddressSync([Buffer.from("psm"), usdcMintPubkey.toBuffer()], program.programId);
             const [psmAuthority] = PublicKey.findProgramAddressSync([Buffer.from("psm_authority")], program.programId);
+            const [psmFeeVault] = PublicKey.findProgramAddressSync([Buffer.from("psm_fee_vault"), usdcMintPubkey.toBuffer()], program.programId);
 
             const userUsdcAta = getAssociatedTokenAddressSync(usdcMintPubkey, wallet.publicKey);
             const userUsdtAta = getAssociatedTokenAddressSync(usdtMint, wallet.publicKey);
 
             if (!psmOracle) throw new Error("PSM Oracle not found. Please wait for balance refresh or configure PSM.");
 
-            const tx = await program.methods.swapUsdcToUsdt(amountBN)
+            // This page shows no quote, so the swaps accept any output (min_amount_out = 0).
+            const tx = await program.methods.swapUsdcToUsdt(amountBN, new BN(0))
                 .accounts({
                     user: wallet.publicKey,
                     psmConfig,
@@ -188,6 +190,7 @@ export default function RemitWizard() {
                     userTokenAccount: userUsdcAta,
                     usdtMint,
                     userUsdtAccount: userUsdtAta,
+                    psmFeeVault,
                     psmAuthority,
                     globalState,
                     tokenProgram: TOKEN_PROGRAM_ID,
@@ -326,6 +329,7 @@ export default function RemitWizard() {
             const [psmVault] = PublicKey.findProgramAddressSync([Buffer.from("psm_vault"), usdcMintPubkey.toBuffer()], program.programId);
             const [psmConfig] = PublicKey.findProgramAddressSync([Buffer.from("psm"), usdcMintPubkey.toBuffer()], program.programId);
             const [psmAuthority] = PublicKey.findProgramAddressSync([Buffer.from("psm_authority")], program.programId);
+            const [psmFeeVault] = PublicKey.findProgramAddressSync([Buffer.from("psm_fee_vault"), usdcMintPubkey.toBuffer()], program.programId);
 
             const userUsdcAta = getAssociatedTokenAddressSync(usdcMintPubkey, actingUserPubkey);
             const userUsdtAta = getAssociatedTokenAddressSync(usdtMint, actingUserPubkey);
@@ -336,7 +340,7 @@ export default function RemitWizard() {
 
             if (isLocalReceiver) {
                 // If using the built-in wallet, we must build and sign the Tx manually with the keypair
-                const ix = await program.methods.swapUsdtToUsdc(amountBN)
+                const ix = await program.methods.swapUsdtToUsdc(amountBN, new BN(0))
                     .accounts({
                         user: actingUserPubkey,
                         psmConfig,
@@ -346,6 +350,7 @@ export default function RemitWizard() {
                         userTokenAccount: userUsdcAta,
                         usdtMint,
                         userUsdtAccount: userUsdtAta,
+                        psmFeeVault,
                         globalState,
                         psmAuthority,
                         tokenProgram: TOKEN_PROGRAM_ID,
@@ -364,7 +369,7 @@ export default function RemitWizard() {
 
             } else {
                 // Standard mode: user signs with their browser wallet
-                txSignature = await program.methods.swapUsdtToUsdc(amountBN)
+                txSignature = await program.methods.swapUsdtToUsdc(amountBN, new BN(0))
                     .accounts({
                         user: actingUserPubkey,
                         psmConfig,
@@ -374,6 +379,7 @@ export default function RemitWizard() {
                         userTokenAccount: userUsdcAta,
                         usdtMint,
                         userUsdtAccount: userUsdtAta,
+                        psmFeeVault,
                         globalState,
                         psmAuthority,
                         tokenProgram: TOKEN_PROGRAM_ID,
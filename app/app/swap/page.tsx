@@ -80,16 +80,20 @@ export default function SwapPage() {
             const [psmVault] = PublicKey.findProgramAddressSync([Buffer.from("psm_vault"), usdcMintPubkey.toBuffer()], program.programId);
             const [psmConfigPda] = PublicKey.findProgramAddressSync([Buffer.from("psm"), usdcMintPubkey.toBuffer()], program.programId);
             const [psmAuthority] = PublicKey.findProgramAddressSync([Buffer.from("psm_authority")], program.programId);
+            const [psmFeeVault] = PublicKey.findProgramAddressSync([Buffer.from("psm_fee_vault"), usdcMintPubkey.toBuffer()], program.programId);
 
             if (direction === "USDC_TO_USDT") {
                 setStatus("Swapping USDC to USDT...");
-                const tx = await program.methods.swapUsdcToUsdt(amountBN)
+                // This page shows no quote, so the swaps accept any output (min_amount_out = 0).
+                const tx = await program.methods.swapUsdcToUsdt(amountBN, new BN(0))
                     .accounts({
                         user: wallet.publicKey,
                         psmConfig: psmConfigPda,
                         tokenMint: usdcMintPubkey,
+                        oracle: psmConfig?.oracle,
                         psmVault: psmVault,
                         usdtMint: usdtMint,
+                        psmFeeVault: psmFeeVault,
                         psmAuthority: psmAuthority,
                         globalState: globalState,
                         tokenProgram: TOKEN_PROGRAM_ID,
@@ -99,13 +103,15 @@ export default function SwapPage() {
                 setStatus(`Success! Swapped ${swapAmount} USDC to USDT. Tx: ${tx}`);
             } else {
                 setStatus("Swapping USDT to USDC...");
-                const tx = await program.methods.swapUsdtToUsdc(amountBN)
+                const tx = await program.methods.swapUsdtToUsdc(amountBN, new BN(0))
                     .accounts({
                         user: wallet.publicKey,
                         psmConfig: psmConfigPda,
                         tokenMint: usdcMintPubkey,
+                        oracle: psmConfig?.oracle,
                         psmVault: psmVault,
                         usdtMint: usdtMint,
+                        psmFeeVault: psmFeeVault,
                         psmAuthority: psmAuthority,
                         globalState: globalState,
                         tokenProgram: TOKEN_PROGRAM_ID,
@@ -209,7 +215,7 @@ export default function SwapPage() {
                 {psmConfig && (
                     <div className="mt-6 p-4 bg-teal-500/5 rounded-xl border border-teal-500/10 text-center">
                         <p className="text-xs text-teal-400">
-                            PSM Fee: {psmConfig.feeBasisPoints.toNumber() / 100}% | Total Minted: {psmConfig.totalMinted.toNumber() / 1_000_000} USDT
+                            PSM Fee: {psmConfig.tinBps.toNumber() / 100}% in / {psmConfig.toutBps.toNumber() / 100}% out | Total Minted: {psmConfig.totalMinted.toNumber() / 1_000_000} USDT
                         </p>
                     </div>
                 )}
@@ -0,0 +1,508 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{AuctionParams, CollateralParams, OracleSource, PsmParams, RevenueSource, Role, TreasuryParams};
+
+#[event]
+pub struct Initialized {
+    pub admin: Pubkey,
+    pub usdt_mint: Pubkey,
+}
+
+#[event]
+pub struct CollateralConfigured {
+    pub collateral_mint: Pubkey,
+    pub oracle: Pubkey,
+    pub oracle_source: OracleSource,
+    pub mcr: u64,
+    pub ltr: u64,
+    pub liquidation_penalty: u64,
+    pub price: u64, // Seeds the price cache
+}
+
+#[event]
+pub struct CollateralDeposited {
+    pub owner: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub amount: u64,
+    pub old_collateral: u64,
+    pub new_collateral: u64,
+}
+
+#[event]
+pub struct CollateralWithdrawn {
+    pub owner: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub amount: u64,
+    pub old_collateral: u64,
+    pub new_collateral: u64,
+    pub price: Option<u64>, // None when the position has no debt and no price was read
+}
+
+#[event]
+pub struct UsdtMinted {
+    pub owner: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub amount: u64,
+    pub old_debt: u64, // Including fees accrued up to this instruction
+    pub new_debt: u64,
+    pub price: u64,
+}
+
+#[event]
+pub struct UsdtBurned {
+    pub owner: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub amount: u64, // Actually burned; requests above the debt are capped
+    pub old_debt: u64,
+    pub new_debt: u64,
+}
+
+#[event]
+pub struct AuctionKicked {
+    pub auction: Pubkey,
+    pub owner: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub keeper: Pubkey,
+    pub lot: u64,
+    pub tab: u64,
+    pub debt: u64,
+    pub price: u64, // Cached price the auction starts from
+    pub top: u64,
+    pub incentive: u64, // Paid to the keeper from surplus
+}
+
+#[event]
+pub struct AuctionTaken {
+    pub auction: Pubkey,
+    pub taker: Pubkey,
+    pub price: u64,
+    pub collateral_bought: u64,
+    pub paid: u64,
+    pub lot: u64, // Left after this take
+    pub tab: u64,
+    pub collateral_returned: u64, // Lot returned to the owner once the tab is raised
+    pub shortfall: u64,           // Debt left unraised when the lot ran out
+}
+
+#[event]
+pub struct AuctionRedone {
+    pub auction: Pubkey,
+    pub keeper: Pubkey,
+    pub price: u64,
+    pub top: u64,
+    pub incentive: u64,
+}
+
+#[event]
+pub struct LiquidationParamsUpdated {
+    pub collateral_mint: Pubkey,
+    pub old_close_factor_bps: u64,
+    pub old_dust: u64,
+    pub old_target_cr: u64,
+    pub close_factor_bps: u64,
+    pub dust: u64,
+    pub target_cr: u64,
+}
+
+#[event]
+pub struct AuctionParamsUpdated {
+    pub collateral_mint: Pubkey,
+    pub old_params: AuctionParams,
+    pub new_params: AuctionParams,
+}
+
+#[event]
+pub struct BadDebtRecognized {
+    pub position: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub amount: u64,
+    pub bad_debt: u64, // Outstanding system-wide after this recognition
+}
+
+#[event]
+pub struct BadDebtCovered {
+    pub from_surplus: u64,
+    pub from_backstop: u64,
+    pub bad_debt: u64, // Left uncovered
+    pub backstop_limit: u64,
+}
+
+#[event]
+pub struct BackstopLimitUpdated {
+    pub old_limit: u64,
+    pub new_limit: u64,
+}
+
+#[event]
+pub struct TreasuryParamsUpdated {
+    pub old_params: TreasuryParams,
+    pub new_params: TreasuryParams,
+    pub old_fee_recipient: Pubkey,
+    pub fee_recipient: Pubkey,
+}
+
+#[event]
+pub struct TreasuryParamsQueued {
+    pub params: TreasuryParams,
+    pub fee_recipient: Pubkey,
+    pub eta: i64,
+}
+
+#[event]
+pub struct SurplusWithdrawn {
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub surplus: u64,  // Left after the withdrawal
+    pub bad_debt: u64, // Outstanding, which surplus withdrawals must leave covered
+}
+
+#[event]
+pub struct PauseUpdated {
+    pub old_paused: bool,
+    pub new_paused: bool,
+}
+
+#[event]
+pub struct PositionFreezeUpdated {
+    pub owner: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub old_frozen: bool,
+    pub new_frozen: bool,
+}
+
+#[event]
+pub struct MockPriceUpdated {
+    pub seed: Pubkey,
+    pub old_price: u64,
+    pub new_price: u64,
+}
+
+#[event]
+pub struct RoleGranted {
+    pub role: Role,
+    pub member: Pubkey,
+    pub previous: Pubkey,
+}
+
+#[event]
+pub struct RoleRevoked {
+    pub role: Role,
+    pub member: Pubkey,
+}
+
+#[event]
+pub struct StabilityFeeUpdated {
+    pub collateral_mint: Pubkey,
+    pub old_fee_bps: u64,
+    pub new_fee_bps: u64,
+}
+
+#[event]
+pub struct StabilityFeesCollected {
+    pub collateral_mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct DebtCeilingUpdated {
+    pub collateral_mint: Pubkey,
+    pub old_ceiling: u64,
+    pub new_ceiling: u64,
+}
+
+#[event]
+pub struct CollateralParamsQueued {
+    pub collateral_mint: Pubkey,
+    pub params: CollateralParams,
+    pub eta: i64,
+}
+
+#[event]
+pub struct CollateralParamsUpdated {
+    pub collateral_mint: Pubkey,
+    pub old_params: CollateralParams,
+    pub new_params: CollateralParams,
+}
+
+#[event]
+pub struct OracleParamsUpdated {
+    pub mint: Pubkey, // Collateral or PSM backing mint
+    pub old_max_staleness_secs: u64,
+    pub old_max_confidence_bps: u64,
+    pub max_staleness_secs: u64,
+    pub max_confidence_bps: u64,
+}
+
+#[event]
+pub struct ExtraOraclesUpdated {
+    pub collateral_mint: Pubkey,
+    pub old_oracles: Vec<Pubkey>,
+    pub old_max_deviation_bps: u64,
+    pub oracles: Vec<Pubkey>,
+    pub max_deviation_bps: u64,
+}
+
+#[event]
+pub struct GovernancePricePosted {
+    pub feed_id: [u8; 32],
+    pub price: u64,
+    pub publish_time: i64,
+}
+
+/// Logged just before the read fails with `OracleDeviationExceeded`, so it only ever appears
+/// in the logs of failed transactions; nothing is recorded in state. Monitors must include
+/// failed transactions (as `logsSubscribe` does) to see it.
+#[event]
+pub struct OracleCircuitBreakerTripped {
+    pub collateral_mint: Pubkey,
+    pub prices: Vec<u64>,
+    pub median: u64,
+    pub max_deviation_bps: u64,
+}
+
+#[event]
+pub struct PriceCachePoked {
+    pub collateral_mint: Pubkey,
+    pub current_price: u64,
+    pub next_price: u64,
+}
+
+#[event]
+pub struct PriceCacheDelayUpdated {
+    pub collateral_mint: Pubkey,
+    pub old_delay_secs: u64,
+    pub new_delay_secs: u64,
+    pub effective_at: i64, // Later than now for a reduction
+}
+
+#[event]
+pub struct PriceCacheFreezeUpdated {
+    pub collateral_mint: Pubkey,
+    pub frozen: bool,
+    pub current_price: u64,
+}
+
+#[event]
+pub struct GlobalDebtCeilingUpdated {
+    pub old_ceiling: u64,
+    pub new_ceiling: u64,
+}
+
+#[event]
+pub struct PsmSwapped {
+    pub user: Pubkey,
+    pub token_mint: Pubkey,
+    pub to_usdt: bool,
+    pub amount_in: u64,  // Backing tokens in (to_usdt) or USDT in
+    pub amount_out: u64, // USDT out (to_usdt) or backing tokens out
+    pub fee: u64,        // USDT sent to the PSM fee vault
+    pub price: u64,
+}
+
+#[event]
+pub struct PsmConfigured {
+    pub token_mint: Pubkey,
+    pub oracle: Pubkey,
+    pub oracle_source: OracleSource,
+    pub tin_bps: u64,
+    pub tout_bps: u64,
+}
+
+#[event]
+pub struct PsmFeesUpdated {
+    pub token_mint: Pubkey,
+    pub old_tin_bps: u64,
+    pub old_tout_bps: u64,
+    pub tin_bps: u64,
+    pub tout_bps: u64,
+}
+
+#[event]
+pub struct PsmParamsQueued {
+    pub token_mint: Pubkey,
+    pub params: PsmParams,
+    pub eta: i64,
+}
+
+#[event]
+pub struct PsmParamsUpdated {
+    pub token_mint: Pubkey,
+    pub old_params: PsmParams,
+    pub new_params: PsmParams,
+}
+
+#[event]
+pub struct PsmLimitsUpdated {
+    pub token_mint: Pubkey,
+    pub old_mint_cap: u64,
+    pub old_window_secs: i64,
+    pub old_window_limit: u64,
+    pub old_min_price: u64,
+    pub old_max_price: u64,
+    pub mint_cap: u64,
+    pub window_secs: i64,
+    pub window_limit: u64,
+    pub min_price: u64,
+    pub max_price: u64,
+}
+
+#[event]
+pub struct RevenueCollected {
+    pub source: RevenueSource,
+    pub amount: u64,
+    pub to_surplus: u64,
+    pub to_liquidator: u64,
+    pub to_fee_recipient: u64,
+}
+
+#[event]
+pub struct SolvencyChecked {
+    pub total_supply: u64,
+    pub cdp_supply: u64,
+    pub psm_supply: u64,
+    pub mint_supply: u64,
+}
+
+#[event]
+pub struct TimelockDelayUpdated {
+    pub old_delay: i64,
+    pub new_delay: i64,
+    pub effective_at: i64, // Later than now for a reduction
+}
+
+#[event]
+pub struct ChangeQueued {
+    pub collateral_mint: Pubkey,
+    pub oracle: Pubkey,
+    pub oracle_source: OracleSource,
+    pub mcr: u64,
+    pub ltr: u64,
+    pub liquidation_penalty: u64,
+    pub extra_oracles: Vec<Pubkey>,
+    pub max_deviation_bps: u64,
+    pub eta: i64,
+}
+
+#[event]
+pub struct ChangeExecuted {
+    pub collateral_mint: Pubkey,
+    pub oracle: Pubkey,
+    pub oracle_source: OracleSource,
+    pub mcr: u64,
+    pub ltr: u64,
+    pub liquidation_penalty: u64,
+}
+
+#[event]
+pub struct ChangeCancelled {
+    pub collateral_mint: Pubkey,
+}
+
+#[event]
+pub struct MultisigUpdated {
+    pub multisig: Pubkey,
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8,
+    pub owner_set_seqno: u32,
+}
+
+#[event]
+pub struct MultisigTransactionProposed {
+    pub multisig: Pubkey,
+    pub transaction: Pubkey,
+    pub index: u64,
+    pub proposer: Pubkey,
+}
+
+#[event]
+pub struct MultisigTransactionApproved {
+    pub multisig: Pubkey,
+    pub transaction: Pubkey,
+    pub approver: Pubkey,
+    pub approvals: u8,
+}
+
+#[event]
+pub struct MultisigTransactionExecuted {
+    pub multisig: Pubkey,
+    pub transaction: Pubkey,
+    pub index: u64,
+}
+
+#[event]
+pub struct AdminTransferProposed {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+}
+
+#[event]
+pub struct AdminTransferAccepted {
+    pub previous_admin: Pubkey,
+    pub new_admin: Pubkey,
+}
+
+#[event]
+pub struct AdminTransferCancelled {
+    pub admin: Pubkey,
+    pub cancelled_admin: Pubkey,
+}
+
+#[event]
+pub struct GlobalSettled {
+    pub settled_at: i64,
+    pub wait_secs: i64,
+}
+
+#[event]
+pub struct CollateralSettled {
+    pub collateral_mint: Pubkey,
+    pub price: u64,
+    pub total_debt: u64,
+}
+
+#[event]
+pub struct AuctionSettled {
+    pub auction: Pubkey,
+    pub position: Pubkey,
+    pub lot: u64,  // Collateral returned to the position
+    pub debt: u64, // Debt returned to the position, penalty dropped
+}
+
+#[event]
+pub struct PositionSettled {
+    pub owner: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub debt: u64,
+    pub collateral_owed: u64,    // Kept for USDT redemptions
+    pub collateral_returned: u64,
+}
+
+#[event]
+pub struct SettlementFixed {
+    pub redeemable_supply: u64,
+    pub surplus: u64,
+    pub backstop: u64,
+    pub psm_fees: u64,
+}
+
+#[event]
+pub struct SettlementPoolOpened {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub amount: u64,
+    pub psm: bool,
+}
+
+#[event]
+pub struct UsdtRedeemed {
+    pub holder: Pubkey,
+    pub amount: u64,
+    pub burned: u64, // Holder's total so far
+}
+
+#[event]
+pub struct RedemptionCashed {
+    pub holder: Pubkey,
+    pub pool: Pubkey,
+    pub amount: u64,
+}
@@ -1,10 +1,17 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
 use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer, MintTo, Burn};
+use crate::events::*;
 use crate::state::*;
-use crate::utils::get_price;
+use crate::utils::{auction_price, bps_fee, collateral_price, collateral_value, get_price, settlement_split, token_amount, validate_oracle, validate_oracle_params, validate_primary_oracle, PriceBias};
+
+#[cfg(all(feature = "mock-oracle", feature = "mainnet"))]
+compile_error!("`mock-oracle` lets the admin set prices and must not ship to mainnet; build with `--no-default-features --features mainnet`.");
 
 declare_id!("5YNSWuk2aqtejuBhz8Tv1xEbxE8rh3BbsPDFzk25LpYw");
 
+pub mod events;
 pub mod state;
 pub mod utils;
 
@@ -29,6 +36,24 @@ pub struct Initialize<'info> {
         mint::authority = global_state,
     )]
     pub usdt_mint: Account<'info, token::Mint>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"roles"],
+        bump,
+        space = Roles::LEN
+    )]
+    pub roles: Account<'info, Roles>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"treasury"],
+        bump,
+        space = Treasury::LEN
+    )]
+    pub treasury: Account<'info, Treasury>,
     
     #[account(mut)]
     pub admin: Signer<'info>,
@@ -41,35 +66,63 @@ pub struct Initialize<'info> {
 pub fn initialize_handler(ctx: Context<Initialize>) -> Result<()> {
     let global_state = &mut ctx.accounts.global_state;
     global_state.admin = ctx.accounts.admin.key();
+    global_state.pending_admin = Pubkey::default();
     global_state.usdt_mint = ctx.accounts.usdt_mint.key();
     global_state.total_supply = 0;
     global_state.paused = false;
     global_state.bump = ctx.bumps.global_state;
+    global_state.timelock_delay = MIN_TIMELOCK_DELAY;
+    global_state.debt_ceiling = u64::MAX;
+    global_state.total_debt = 0;
+    ctx.accounts.roles.bump = ctx.bumps.roles;
+    ctx.accounts.treasury.params = TreasuryParams::default();
+    ctx.accounts.treasury.bump = ctx.bumps.treasury;
     msg!("Global State Initialized. Admin: {}, Mint: {}", global_state.admin, global_state.usdt_mint);
+    emit!(Initialized { admin: global_state.admin, usdt_mint: global_state.usdt_mint });
     Ok(())
 }
 
 // --- Configure Collateral ---
 #[derive(Accounts)]
-#[instruction(collateral_mint: Pubkey)]
+#[instruction(collateral_mint: Pubkey, oracle: Pubkey)]
 pub struct ConfigureCollateral<'info> {
     #[account(
         init_if_needed,
-        payer = admin,
+        payer = authority,
         seeds = [b"collateral", collateral_mint.key().as_ref()],
         bump,
         space = CollateralConfig::LEN
     )]
     pub collateral_config: Account<'info, CollateralConfig>,
 
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [b"price_cache", collateral_mint.key().as_ref()],
+        bump,
+        space = PriceCache::LEN
+    )]
+    pub price_cache: Account<'info, PriceCache>,
+
     #[account(
         seeds = [b"global_state"],
         bump = global_state.bump
     )]
     pub global_state: Account<'info, GlobalState>,
 
+    #[account(
+        seeds = [b"roles"],
+        bump = roles.bump,
+        constraint = roles.is_authorized(Role::RiskManager, &global_state, &authority.key()) @ CustomErrorCode::Unauthorized
+    )]
+    pub roles: Account<'info, Roles>,
+
+    /// CHECK: Validated as a price feed in the handler
+    #[account(address = oracle @ CustomErrorCode::InvalidOracle)]
+    pub oracle_feed: AccountInfo<'info>,
+
     #[account(mut)]
-    pub admin: Signer<'info>,
+    pub authority: Signer<'info>,
 
     pub system_program: Program<'info, System>,
 }
@@ -78,18 +131,193 @@ pub fn configure_collateral_handler(
     ctx: Context<ConfigureCollateral>,
     collateral_mint: Pubkey,
     oracle: Pubkey,
+    oracle_source: OracleSource,
     mcr: u64,
     ltr: u64,
     liquidation_penalty: u64,
 ) -> Result<()> {
     let config = &mut ctx.accounts.collateral_config;
+    // Only the initial listing is immediate; later updates go through the timelock queue.
+    if config.is_initialized() { return err!(CustomErrorCode::TimelockRequired); }
+    CollateralConfig::validate_risk_params(mcr, ltr, liquidation_penalty)?;
+    validate_primary_oracle(&ctx.accounts.oracle_feed, &oracle_source)?;
+    let now = Clock::get()?.unix_timestamp;
+    let price = get_price(&ctx.accounts.oracle_feed, &oracle_source, DEFAULT_MAX_STALENESS_SECS, DEFAULT_MAX_CONFIDENCE_BPS, PriceBias::Mid)?;
+    ctx.accounts.price_cache.seed(collateral_mint, price, now);
+    ctx.accounts.price_cache.bump = ctx.bumps.price_cache;
     config.collateral_mint = collateral_mint;
-    config.oracle = oracle;
-    config.mcr = mcr;
-    config.ltr = ltr;
-    config.liquidation_penalty = liquidation_penalty;
+    config.set_risk_params(oracle, oracle_source, mcr, ltr, liquidation_penalty);
     config.bump = ctx.bumps.collateral_config;
+    config.cumulative_rate = RATE_PRECISION;
+    config.last_accrual = now;
+    config.debt_ceiling = u64::MAX;
+    config.max_staleness_secs = DEFAULT_MAX_STALENESS_SECS;
+    config.max_confidence_bps = DEFAULT_MAX_CONFIDENCE_BPS;
+    config.max_deviation_bps = DEFAULT_MAX_DEVIATION_BPS;
+    config.auction = AuctionParams::default();
+    config.close_factor_bps = BPS_DENOMINATOR;
     msg!("Collateral Configured. Mint: {}, MCR: {}", collateral_mint, mcr);
+    emit!(CollateralConfigured { collateral_mint, oracle, oracle_source, mcr, ltr, liquidation_penalty, price });
+    Ok(())
+}
+
+// --- Timelock ---
+#[derive(Accounts)]
+pub struct SetTimelockDelay<'info> {
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump, has_one = admin @ CustomErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    pub admin: Signer<'info>,
+}
+
+/// Raising the delay applies at once. A reduction only applies once the current delay has
+/// passed, so changes queued in the meantime can't be rushed through.
+pub fn set_timelock_delay_handler(ctx: Context<SetTimelockDelay>, delay: i64) -> Result<()> {
+    if delay < MIN_TIMELOCK_DELAY { return err!(CustomErrorCode::InvalidTimelockDelay); }
+    let now = Clock::get()?.unix_timestamp;
+    let global_state = &mut ctx.accounts.global_state;
+    let old_delay = global_state.timelock_delay_at(now);
+    let effective_at = if delay < old_delay { now.checked_add(old_delay).ok_or(CustomErrorCode::MathOverflow)? } else { now };
+    global_state.timelock_delay = old_delay.max(delay);
+    (global_state.pending_timelock_delay, global_state.timelock_delay_eta) = if delay < old_delay { (delay, effective_at) } else { (0, 0) };
+    emit!(TimelockDelayUpdated { old_delay, new_delay: delay, effective_at });
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(oracle: Pubkey)]
+pub struct QueueChange<'info> {
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"pending_change", collateral_config.collateral_mint.as_ref()],
+        bump,
+        space = PendingChange::LEN
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+    #[account(seeds = [b"collateral", collateral_config.collateral_mint.as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Account<'info, CollateralConfig>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(seeds = [b"roles"], bump = roles.bump, constraint = roles.is_authorized(Role::RiskManager, &global_state, &authority.key()) @ CustomErrorCode::Unauthorized)]
+    pub roles: Account<'info, Roles>,
+    /// CHECK: Validated as a price feed in the handler
+    #[account(address = oracle @ CustomErrorCode::InvalidOracle)]
+    pub oracle_feed: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Checks the oracles to medianize with `oracle` and lays them out in config slots. Each
+/// must be passed in `remaining_accounts` so it can be validated against its source.
+fn extra_oracle_slots(oracle: Pubkey, extra_oracles: &[OracleFeed], max_deviation_bps: u64, remaining_accounts: &[AccountInfo]) -> Result<[OracleFeed; MAX_EXTRA_ORACLES]> {
+    if extra_oracles.len() > MAX_EXTRA_ORACLES { return err!(CustomErrorCode::TooManyOracles); }
+    if max_deviation_bps == 0 || max_deviation_bps > BPS_DENOMINATOR { return err!(CustomErrorCode::InvalidOracleParams); }
+    let mut slots = [OracleFeed::default(); MAX_EXTRA_ORACLES];
+    for (i, feed) in extra_oracles.iter().enumerate() {
+        let duplicate = feed.oracle == oracle || extra_oracles[..i].iter().any(|f| f.oracle == feed.oracle);
+        if feed.oracle == Pubkey::default() || duplicate { return err!(CustomErrorCode::InvalidOracle); }
+        let info = remaining_accounts.iter().find(|a| a.key == &feed.oracle).ok_or(CustomErrorCode::MissingOracleAccount)?;
+        validate_oracle(info, &feed.source)?;
+        slots[i] = *feed;
+    }
+    Ok(slots)
+}
+
+/// Queues a full set of risk parameters, oracles included, to take effect after the
+/// timelock delay.
+#[allow(clippy::too_many_arguments)]
+pub fn queue_change_handler(
+    ctx: Context<QueueChange>,
+    oracle: Pubkey,
+    oracle_source: OracleSource,
+    mcr: u64,
+    ltr: u64,
+    liquidation_penalty: u64,
+    extra_oracles: Vec<OracleFeed>,
+    max_deviation_bps: u64,
+) -> Result<()> {
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    CollateralConfig::validate_risk_params(mcr, ltr, liquidation_penalty)?;
+    validate_primary_oracle(&ctx.accounts.oracle_feed, &oracle_source)?;
+    let slots = extra_oracle_slots(oracle, &extra_oracles, max_deviation_bps, ctx.remaining_accounts)?;
+    let now = Clock::get()?.unix_timestamp;
+    let eta = now.checked_add(ctx.accounts.global_state.timelock_delay_at(now)).ok_or(CustomErrorCode::MathOverflow)?;
+    let change = &mut ctx.accounts.pending_change;
+    change.collateral_mint = ctx.accounts.collateral_config.collateral_mint;
+    change.oracle = oracle;
+    change.oracle_source = oracle_source;
+    change.mcr = mcr;
+    change.ltr = ltr;
+    change.liquidation_penalty = liquidation_penalty;
+    change.extra_oracles = slots;
+    change.max_deviation_bps = max_deviation_bps;
+    change.eta = eta;
+    change.bump = ctx.bumps.pending_change;
+    emit!(ChangeQueued {
+        collateral_mint: change.collateral_mint,
+        oracle,
+        oracle_source,
+        mcr,
+        ltr,
+        liquidation_penalty,
+        extra_oracles: extra_oracles.iter().map(|f| f.oracle).collect(),
+        max_deviation_bps,
+        eta,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ResolveChange<'info> {
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"pending_change", collateral_config.collateral_mint.as_ref()],
+        bump = pending_change.bump
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+    #[account(mut, seeds = [b"collateral", collateral_config.collateral_mint.as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Account<'info, CollateralConfig>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(seeds = [b"roles"], bump = roles.bump, constraint = roles.is_authorized(Role::RiskManager, &global_state, &authority.key()) @ CustomErrorCode::Unauthorized)]
+    pub roles: Account<'info, Roles>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+pub fn execute_change_handler(ctx: Context<ResolveChange>) -> Result<()> {
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    let change = &ctx.accounts.pending_change;
+    if Clock::get()?.unix_timestamp < change.eta { return err!(CustomErrorCode::TimelockNotElapsed); }
+    let config = &mut ctx.accounts.collateral_config;
+    config.set_risk_params(change.oracle, change.oracle_source, change.mcr, change.ltr, change.liquidation_penalty);
+    if config.extra_oracles != change.extra_oracles || config.max_deviation_bps != change.max_deviation_bps {
+        let active = |feeds: &[OracleFeed]| feeds.iter().filter(|f| f.oracle != Pubkey::default()).map(|f| f.oracle).collect();
+        emit!(ExtraOraclesUpdated {
+            collateral_mint: config.collateral_mint,
+            old_oracles: active(&config.extra_oracles),
+            old_max_deviation_bps: config.max_deviation_bps,
+            oracles: active(&change.extra_oracles),
+            max_deviation_bps: change.max_deviation_bps,
+        });
+        config.extra_oracles = change.extra_oracles;
+        config.max_deviation_bps = change.max_deviation_bps;
+    }
+    emit!(ChangeExecuted {
+        collateral_mint: change.collateral_mint,
+        oracle: change.oracle,
+        oracle_source: change.oracle_source,
+        mcr: change.mcr,
+        ltr: change.ltr,
+        liquidation_penalty: change.liquidation_penalty,
+    });
+    Ok(())
+}
+
+pub fn cancel_change_handler(ctx: Context<ResolveChange>) -> Result<()> {
+    emit!(ChangeCancelled { collateral_mint: ctx.accounts.pending_change.collateral_mint });
     Ok(())
 }
 
@@ -166,8 +394,16 @@ pub fn deposit_collateral_handler(ctx: Context<DepositCollateral>, amount: u64)
         position.collateral_mint = ctx.accounts.collateral_mint.key();
         position.bump = ctx.bumps.position;
     }
+    let old_collateral = position.collateral_amount;
     position.collateral_amount = position.collateral_amount.checked_add(amount).unwrap();
     position.last_updated = Clock::get()?.unix_timestamp;
+    emit!(CollateralDeposited {
+        owner: position.owner,
+        collateral_mint: position.collateral_mint,
+        amount,
+        old_collateral,
+        new_collateral: position.collateral_amount,
+    });
     Ok(())
 }
 
@@ -178,7 +414,7 @@ pub struct MintUsdt<'info> {
     pub owner: Signer<'info>,
     #[account(mut, seeds = [b"position", owner.key().as_ref(), collateral_mint.key().as_ref()], bump = position.bump)]
     pub position: Account<'info, Position>,
-    #[account(seeds = [b"collateral", collateral_mint.key().as_ref()], bump = collateral_config.bump)]
+    #[account(mut, seeds = [b"collateral", collateral_mint.key().as_ref()], bump = collateral_config.bump)]
     pub collateral_config: Account<'info, CollateralConfig>,
     /// CHECK: Validated against config
     #[account(address = collateral_config.oracle)]
@@ -188,27 +424,32 @@ pub struct MintUsdt<'info> {
     #[account(init_if_needed, payer = owner, associated_token::mint = usdt_mint, associated_token::authority = owner)]
     pub user_usdt_account: Account<'info, TokenAccount>,
     pub collateral_mint: Account<'info, Mint>,
-    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump)]
     pub global_state: Account<'info, GlobalState>,
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
-pub fn mint_usdt_handler(ctx: Context<MintUsdt>, amount: u64) -> Result<()> {
+pub fn mint_usdt_handler(ctx: Context<MintUsdt>, amount: u64, max_price: Option<u64>) -> Result<()> {
     if ctx.accounts.global_state.paused { return err!(CustomErrorCode::Paused); }
     if ctx.accounts.position.is_frozen { return err!(CustomErrorCode::Frozen); }
 
-    let price = get_price(&ctx.accounts.oracle)?;
+    let now = Clock::get()?.unix_timestamp;
+    let config = &mut ctx.accounts.collateral_config;
+    let price = collateral_price(config, &ctx.accounts.oracle, ctx.remaining_accounts, PriceBias::Collateral)?;
+    if max_price.is_some_and(|max| price > max) { return err!(CustomErrorCode::PriceAboveMax); }
+    config.accrue(now)?;
     let position = &mut ctx.accounts.position;
-    let collateral_val = (position.collateral_amount as u128)
-        .checked_mul(price as u128).unwrap()
-        .checked_div(10u128.pow(ctx.accounts.collateral_mint.decimals as u32)).unwrap() as u64;
-        
-    let new_debt = position.debt_amount.checked_add(amount).unwrap();
-    let mcr_percent = ctx.accounts.collateral_config.mcr;
-    let required_val = (new_debt as u128).checked_mul(mcr_percent as u128).unwrap().checked_div(100).unwrap();
-    if (collateral_val as u128) < required_val { return err!(CustomErrorCode::BelowMcr); }
+    let collateral_val = collateral_value(position.collateral_amount, price, ctx.accounts.collateral_mint.decimals)?;
+    let old_debt = config.debt_of(position.normalized_debt)?;
+    let new_debt = old_debt.checked_add(amount).ok_or(CustomErrorCode::MathOverflow)?;
+    config.check_mcr(collateral_val, new_debt)?;
+    config.add_debt(position, amount)?;
+    config.sync_total_debt(&mut ctx.accounts.global_state)?;
+    config.check_debt_ceilings(&ctx.accounts.global_state)?;
+    ctx.accounts.global_state.record_mint(SupplySource::Cdp, amount)?;
+    position.last_updated = now;
     
     let seeds = &[b"global_state".as_ref(), &[ctx.accounts.global_state.bump]];
     let cpi_accounts = MintTo {
@@ -217,8 +458,14 @@ pub fn mint_usdt_handler(ctx: Context<MintUsdt>, amount: u64) -> Result<()> {
         authority: ctx.accounts.global_state.to_account_info(),
     };
     token::mint_to(CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, &[&seeds[..]]), amount)?;
-    position.debt_amount = new_debt;
-    position.last_updated = Clock::get()?.unix_timestamp;
+    emit!(UsdtMinted {
+        owner: position.owner,
+        collateral_mint: position.collateral_mint,
+        amount,
+        old_debt,
+        new_debt: position.debt_amount,
+        price,
+    });
     Ok(())
 }
 
@@ -233,24 +480,54 @@ pub struct BurnUsdt<'info> {
     pub usdt_mint: Account<'info, Mint>,
     #[account(mut, associated_token::mint = usdt_mint, associated_token::authority = owner)]
     pub user_usdt_account: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"collateral", collateral_mint.key().as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Account<'info, CollateralConfig>,
     pub collateral_mint: Account<'info, Mint>,
+    #[account(init_if_needed, payer = owner, seeds = [b"surplus"], bump, token::mint = usdt_mint, token::authority = global_state)]
+    pub surplus_account: Account<'info, TokenAccount>,
     pub token_program: Program<'info, Token>,
-    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump)]
     pub global_state: Account<'info, GlobalState>,
+    pub system_program: Program<'info, System>,
 }
 
+/// Repays debt. Accrued fees are collected first, so the repayment never burns USDT that
+/// supply accounting hasn't seen minted.
 pub fn burn_usdt_handler(ctx: Context<BurnUsdt>, amount: u64) -> Result<()> {
     if ctx.accounts.global_state.paused { return err!(CustomErrorCode::Paused); }
     if ctx.accounts.position.is_frozen { return err!(CustomErrorCode::Frozen); }
+    let now = Clock::get()?.unix_timestamp;
+    let config = &mut ctx.accounts.collateral_config;
+    config.accrue(now)?;
+    collect_fees(config, &mut ctx.accounts.global_state, &ctx.accounts.usdt_mint, &ctx.accounts.surplus_account, &ctx.accounts.token_program)?;
+    let position = &mut ctx.accounts.position;
+    let old_debt = config.debt_of(position.normalized_debt)?;
+    // Burning more than the outstanding debt only burns the debt.
+    let repaid = config.remove_debt(position, amount)?;
+    config.sync_total_debt(&mut ctx.accounts.global_state)?;
+    let burned = ctx.accounts.global_state.record_debt_burn(repaid)?;
+    position.last_updated = now;
     let cpi_accounts = Burn {
         mint: ctx.accounts.usdt_mint.to_account_info(),
         from: ctx.accounts.user_usdt_account.to_account_info(),
         authority: ctx.accounts.owner.to_account_info(),
     };
-    token::burn(CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts), amount)?;
-    let position = &mut ctx.accounts.position;
-    position.debt_amount = if amount > position.debt_amount { 0 } else { position.debt_amount.checked_sub(amount).unwrap() };
-    position.last_updated = Clock::get()?.unix_timestamp;
+    token::burn(CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts), burned)?;
+    if repaid > burned {
+        let cpi_rounding = Transfer {
+            from: ctx.accounts.user_usdt_account.to_account_info(),
+            to: ctx.accounts.surplus_account.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        token::transfer(CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_rounding), repaid - burned)?;
+    }
+    emit!(UsdtBurned {
+        owner: position.owner,
+        collateral_mint: position.collateral_mint,
+        amount: repaid,
+        old_debt,
+        new_debt: position.debt_amount,
+    });
     Ok(())
 }
 
@@ -261,7 +538,7 @@ pub struct WithdrawCollateral<'info> {
     pub owner: Signer<'info>,
     #[account(mut, seeds = [b"position", owner.key().as_ref(), collateral_mint.key().as_ref()], bump = position.bump)]
     pub position: Account<'info, Position>,
-    #[account(seeds = [b"collateral", collateral_mint.key().as_ref()], bump = collateral_config.bump)]
+    #[account(mut, seeds = [b"collateral", collateral_mint.key().as_ref()], bump = collateral_config.bump)]
     pub collateral_config: Account<'info, CollateralConfig>,
     /// CHECK: Validated against config
     #[account(address = collateral_config.oracle)]
@@ -275,23 +552,30 @@ pub struct WithdrawCollateral<'info> {
     #[account(mut, associated_token::mint = collateral_mint, associated_token::authority = owner)]
     pub user_token_account: Account<'info, TokenAccount>,
     pub token_program: Program<'info, Token>,
-    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump)]
     pub global_state: Account<'info, GlobalState>,
 }
 
 pub fn withdraw_collateral_handler(ctx: Context<WithdrawCollateral>, amount: u64) -> Result<()> {
     if ctx.accounts.global_state.paused { return err!(CustomErrorCode::Paused); }
     if ctx.accounts.position.is_frozen { return err!(CustomErrorCode::Frozen); }
+    let now = Clock::get()?.unix_timestamp;
+    let config = &mut ctx.accounts.collateral_config;
+    config.accrue(now)?;
     let position = &mut ctx.accounts.position;
     let new_balance = position.collateral_amount.checked_sub(amount).ok_or(CustomErrorCode::InsufficientCollateral)?;
+    config.sync_total_debt(&mut ctx.accounts.global_state)?;
+    position.debt_amount = config.debt_of(position.normalized_debt)?;
+    let mut price = None;
     if position.debt_amount > 0 {
-        let price = get_price(&ctx.accounts.oracle)?;
-        let val = (new_balance as u128).checked_mul(price as u128).unwrap().checked_div(10u128.pow(ctx.accounts.collateral_mint.decimals as u32)).unwrap() as u64;
-        let req = (position.debt_amount as u128).checked_mul(ctx.accounts.collateral_config.mcr as u128).unwrap().checked_div(100).unwrap();
-        if (val as u128) < req { return err!(CustomErrorCode::BelowMcr); }
+        let p = collateral_price(config, &ctx.accounts.oracle, ctx.remaining_accounts, PriceBias::Collateral)?;
+        let val = collateral_value(new_balance, p, ctx.accounts.collateral_mint.decimals)?;
+        config.check_mcr(val, position.debt_amount)?;
+        price = Some(p);
     }
+    let old_collateral = position.collateral_amount;
     position.collateral_amount = new_balance;
-    position.last_updated = Clock::get()?.unix_timestamp;
+    position.last_updated = now;
     let seeds = &[b"vault_authority".as_ref(), &[ctx.bumps.vault_authority]];
     let cpi_accounts = Transfer {
         from: ctx.accounts.vault_token_account.to_account_info(),
@@ -299,213 +583,1756 @@ pub fn withdraw_collateral_handler(ctx: Context<WithdrawCollateral>, amount: u64
         authority: ctx.accounts.vault_authority.to_account_info(),
     };
     token::transfer(CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, &[&seeds[..]]), amount)?;
+    emit!(CollateralWithdrawn {
+        owner: position.owner,
+        collateral_mint: position.collateral_mint,
+        amount,
+        old_collateral,
+        new_collateral: new_balance,
+        price,
+    });
+    Ok(())
+}
+
+// --- Liquidation Auctions ---
+// An unsafe position is kicked into a Dutch auction instead of being sold to the first
+// liquidator at a fixed discount. Prices start from the delayed cache price.
+
+/// Pays a keeper up to `amount` USDT from surplus and returns what was paid.
+fn pay_keeper<'info>(
+    surplus_account: &Account<'info, TokenAccount>,
+    keeper_usdt_account: &Account<'info, TokenAccount>,
+    global_state: &Account<'info, GlobalState>,
+    token_program: &Program<'info, Token>,
+    amount: u64,
+) -> Result<u64> {
+    let paid = amount.min(surplus_account.amount);
+    if paid > 0 {
+        let seeds = &[b"global_state".as_ref(), &[global_state.bump]];
+        let cpi_accounts = Transfer {
+            from: surplus_account.to_account_info(),
+            to: keeper_usdt_account.to_account_info(),
+            authority: global_state.to_account_info(),
+        };
+        token::transfer(CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, &[&seeds[..]]), paid)?;
+    }
+    Ok(paid)
+}
+
+#[derive(Accounts)]
+pub struct Kick<'info> {
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    #[account(mut, seeds = [b"position", position.owner.as_ref(), collateral_mint.key().as_ref()], bump = position.bump)]
+    pub position: Account<'info, Position>,
+    #[account(init, payer = keeper, space = Auction::LEN, seeds = [b"auction", position.key().as_ref()], bump)]
+    pub auction: Account<'info, Auction>,
+    #[account(mut, seeds = [b"collateral", collateral_mint.key().as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Account<'info, CollateralConfig>,
+    #[account(seeds = [b"price_cache", collateral_mint.key().as_ref()], bump = price_cache.bump)]
+    pub price_cache: Account<'info, PriceCache>,
+    pub collateral_mint: Account<'info, Mint>,
+    #[account(seeds = [b"mint"], bump)]
+    pub usdt_mint: Account<'info, Mint>,
+    #[account(init_if_needed, payer = keeper, seeds = [b"surplus"], bump, token::mint = usdt_mint, token::authority = global_state)]
+    pub surplus_account: Account<'info, TokenAccount>,
+    #[account(init_if_needed, payer = keeper, associated_token::mint = usdt_mint, associated_token::authority = keeper)]
+    pub keeper_usdt_account: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Permissionless: moves debt (plus the liquidation penalty) from an unsafe position into an
+/// auction, along with collateral to sell for it. How much is set by the close factor,
+/// dust and target CR; see `CollateralConfig::kick_amount`. The collateral stays in the vault.
+pub fn kick_handler(ctx: Context<Kick>) -> Result<()> {
+    if ctx.accounts.global_state.paused { return err!(CustomErrorCode::Paused); }
+    let now = Clock::get()?.unix_timestamp;
+    let price = ctx.accounts.price_cache.liquidation_price(now)?;
+    let config = &mut ctx.accounts.collateral_config;
+    config.accrue(now)?;
+    let position = &mut ctx.accounts.position;
+    let debt = config.debt_of(position.normalized_debt)?;
+    let val = collateral_value(position.collateral_amount, price, ctx.accounts.collateral_mint.decimals)?;
+    if !config.is_liquidatable(val, debt)? { return err!(CustomErrorCode::PositionSafe); }
+
+    let mut kicked = config.kick_amount(val, debt)?;
+    // A partial kick takes a proportional share of the collateral, but at least enough to
+    // cover the tab at the cached price. Whatever isn't sold returns once the tab is raised.
+    let mut lot = position.collateral_amount;
+    if kicked < debt {
+        let share = (position.collateral_amount as u128 * kicked as u128 / debt as u128) as u64;
+        let price_floor = if price == 0 { lot } else { token_amount(config.liquidation_tab(kicked)?, price, ctx.accounts.collateral_mint.decimals)? };
+        lot = share.max(price_floor).min(lot);
+        // Debt left on a position with no collateral could only become bad debt later.
+        if lot == position.collateral_amount { kicked = debt; }
+    }
+    let tab = config.liquidation_tab(kicked)?;
+    config.remove_debt(position, kicked)?;
+    config.sync_total_debt(&mut ctx.accounts.global_state)?;
+    position.collateral_amount -= lot;
+    position.last_updated = now;
+
+    let auction = &mut ctx.accounts.auction;
+    auction.position = position.key();
+    auction.owner = position.owner;
+    auction.collateral_mint = position.collateral_mint;
+    auction.keeper = ctx.accounts.keeper.key();
+    auction.lot = lot;
+    auction.tab = tab;
+    auction.debt = kicked;
+    auction.bump = ctx.bumps.auction;
+    auction.reset(price, &config.auction, now)?;
+
+    let incentive = pay_keeper(&ctx.accounts.surplus_account, &ctx.accounts.keeper_usdt_account, &ctx.accounts.global_state, &ctx.accounts.token_program, config.auction.incentive(tab)?)?;
+    emit!(AuctionKicked {
+        auction: auction.key(),
+        owner: auction.owner,
+        collateral_mint: auction.collateral_mint,
+        keeper: auction.keeper,
+        lot,
+        tab,
+        debt: kicked,
+        price,
+        top: auction.top,
+        incentive,
+    });
     Ok(())
 }
 
-// --- Liquidate ---
 #[derive(Accounts)]
-pub struct Liquidate<'info> {
+pub struct Take<'info> {
     #[account(mut)]
-    pub liquidator: Signer<'info>,
-    #[account(mut, seeds = [b"position", position_owner.key().as_ref(), collateral_mint.key().as_ref()], bump = position.bump)]
+    pub taker: Signer<'info>,
+    #[account(mut, seeds = [b"auction", auction.position.as_ref()], bump = auction.bump, has_one = collateral_mint, has_one = keeper)]
+    pub auction: Account<'info, Auction>,
+    #[account(mut, address = auction.position)]
     pub position: Account<'info, Position>,
-    /// CHECK: Position owner
-    pub position_owner: AccountInfo<'info>,
-    #[account(seeds = [b"collateral", collateral_mint.key().as_ref()], bump = collateral_config.bump)]
+    /// CHECK: Receives the auction account's rent when it closes
+    #[account(mut)]
+    pub keeper: AccountInfo<'info>,
+    #[account(mut, seeds = [b"collateral", collateral_mint.key().as_ref()], bump = collateral_config.bump)]
     pub collateral_config: Account<'info, CollateralConfig>,
-    /// CHECK: Oracle
-    #[account(address = collateral_config.oracle)]
-    pub oracle: AccountInfo<'info>,
     #[account(mut, seeds = [b"mint"], bump)]
     pub usdt_mint: Account<'info, Mint>,
-    #[account(mut, associated_token::mint = usdt_mint, associated_token::authority = liquidator)]
-    pub liquidator_usdt_account: Account<'info, TokenAccount>,
-    #[account(mut, associated_token::mint = collateral_mint, associated_token::authority = liquidator)]
-    pub liquidator_collateral_account: Account<'info, TokenAccount>,
+    #[account(mut, associated_token::mint = usdt_mint, associated_token::authority = taker)]
+    pub taker_usdt_account: Account<'info, TokenAccount>,
+    #[account(init_if_needed, payer = taker, associated_token::mint = collateral_mint, associated_token::authority = taker)]
+    pub taker_collateral_account: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"surplus"], bump, token::mint = usdt_mint, token::authority = global_state)]
+    pub surplus_account: Account<'info, TokenAccount>,
     #[account(mut, seeds = [b"vault", collateral_mint.key().as_ref()], bump, token::mint = collateral_mint, token::authority = vault_authority)]
     pub vault_token_account: Account<'info, TokenAccount>,
     /// CHECK: Vault authority
     #[account(seeds = [b"vault_authority"], bump)]
     pub vault_authority: AccountInfo<'info>,
     pub collateral_mint: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
-    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    #[account(init_if_needed, payer = taker, space = SystemDebt::LEN, seeds = [b"system_debt"], bump)]
+    pub system_debt: Account<'info, SystemDebt>,
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(mut, address = treasury.fee_recipient @ CustomErrorCode::MissingFeeRecipient)]
+    pub fee_recipient_account: Option<Account<'info, TokenAccount>>,
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump)]
     pub global_state: Account<'info, GlobalState>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+    pub system_program: Program<'info, System>,
 }
 
-pub fn liquidate_handler(ctx: Context<Liquidate>, amount: u64) -> Result<()> {
+/// Buys up to `amount` collateral at the current auction price, which must not exceed
+/// `max_price`. The debt share of the payment is burned and the penalty is split by the
+/// treasury, with the liquidator's share left with the taker. Once the tab is raised,
+/// leftover collateral returns to the position; if the lot runs out first, the unpaid debt
+/// is recognized as bad debt.
+pub fn take_handler(ctx: Context<Take>, amount: u64, max_price: u64) -> Result<()> {
     if ctx.accounts.global_state.paused { return err!(CustomErrorCode::Paused); }
-    let price = get_price(&ctx.accounts.oracle)?;
-    let position = &mut ctx.accounts.position;
-    let val = (position.collateral_amount as u128).checked_mul(price as u128).unwrap().checked_div(10u128.pow(ctx.accounts.collateral_mint.decimals as u32)).unwrap() as u64;
-    let req = (position.debt_amount as u128).checked_mul(ctx.accounts.collateral_config.mcr as u128).unwrap().checked_div(100).unwrap();
-    if (val as u128) >= req { return err!(CustomErrorCode::PositionSafe); }
-    
-    let repay = if amount > position.debt_amount { position.debt_amount } else { amount };
-    let cpi_burn = Burn { mint: ctx.accounts.usdt_mint.to_account_info(), from: ctx.accounts.liquidator_usdt_account.to_account_info(), authority: ctx.accounts.liquidator.to_account_info() };
-    token::burn(CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_burn), repay)?;
+    let now = Clock::get()?.unix_timestamp;
+    let params = ctx.accounts.collateral_config.auction;
+    let decimals = ctx.accounts.collateral_mint.decimals;
+    let auction = &mut ctx.accounts.auction;
+    let price = auction_price(auction.top, now.saturating_sub(auction.start_time), params.duration_secs);
+    if auction.needs_redo(price, &params, now) { return err!(CustomErrorCode::AuctionNeedsRedo); }
+    if price > max_price { return err!(CustomErrorCode::SlippageExceeded); }
 
-    let seize_val = (repay as u128).checked_mul(100 + ctx.accounts.collateral_config.liquidation_penalty as u128).unwrap().checked_div(100).unwrap();
-    let seize_amt = (seize_val.checked_mul(10u128.pow(ctx.accounts.collateral_mint.decimals as u32)).unwrap().checked_div(price as u128).unwrap() as u64).min(position.collateral_amount);
-    
-    position.debt_amount = position.debt_amount.checked_sub(repay).unwrap();
-    position.collateral_amount = position.collateral_amount.checked_sub(seize_amt).unwrap();
-    position.last_updated = Clock::get()?.unix_timestamp;
+    // Round what the taker owes up, and never sell past the tab.
+    let mut bought = amount.min(auction.lot);
+    let owe = (bought as u128).checked_mul(price as u128).ok_or(CustomErrorCode::MathOverflow)?.div_ceil(10u128.pow(decimals as u32));
+    let mut paid = u64::try_from(owe).map_err(|_| CustomErrorCode::MathOverflow)?;
+    if paid > auction.tab {
+        paid = auction.tab;
+        bought = token_amount(paid, price, decimals)?.min(auction.lot);
+    }
+    let burned = paid.min(auction.debt);
+    let penalty = paid - burned;
 
+    // The tab includes fees accrued up to the kick; collect them before burning.
+    collect_fees(&mut ctx.accounts.collateral_config, &mut ctx.accounts.global_state, &ctx.accounts.usdt_mint, &ctx.accounts.surplus_account, &ctx.accounts.token_program)?;
+    let burnable = ctx.accounts.global_state.record_debt_burn(burned)?;
+    let cpi_burn = Burn { mint: ctx.accounts.usdt_mint.to_account_info(), from: ctx.accounts.taker_usdt_account.to_account_info(), authority: ctx.accounts.taker.to_account_info() };
+    token::burn(CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_burn), burnable)?;
+    let treasury = &mut ctx.accounts.treasury;
+    let (to_surplus, to_liquidator, to_fee_recipient) = treasury.split(penalty, true);
+    treasury.total_penalties = treasury.total_penalties.checked_add(penalty).ok_or(CustomErrorCode::MathOverflow)?;
+    let cpi_penalty = Transfer { from: ctx.accounts.taker_usdt_account.to_account_info(), to: ctx.accounts.surplus_account.to_account_info(), authority: ctx.accounts.taker.to_account_info() };
+    // Debt rounding that CDP supply never minted goes to surplus along with the penalty.
+    token::transfer(CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_penalty), to_surplus + (burned - burnable))?;
+    if to_fee_recipient > 0 {
+        let fee_recipient = ctx.accounts.fee_recipient_account.as_ref().ok_or(CustomErrorCode::MissingFeeRecipient)?;
+        let cpi_fee = Transfer { from: ctx.accounts.taker_usdt_account.to_account_info(), to: fee_recipient.to_account_info(), authority: ctx.accounts.taker.to_account_info() };
+        token::transfer(CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_fee), to_fee_recipient)?;
+    }
+    if penalty > 0 {
+        emit!(RevenueCollected { source: RevenueSource::LiquidationPenalty, amount: penalty, to_surplus, to_liquidator, to_fee_recipient });
+    }
     let seeds = &[b"vault_authority".as_ref(), &[ctx.bumps.vault_authority]];
-    let cpi_transfer = Transfer { from: ctx.accounts.vault_token_account.to_account_info(), to: ctx.accounts.liquidator_collateral_account.to_account_info(), authority: ctx.accounts.vault_authority.to_account_info() };
-    token::transfer(CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_transfer, &[&seeds[..]]), seize_amt)?;
+    let cpi_transfer = Transfer { from: ctx.accounts.vault_token_account.to_account_info(), to: ctx.accounts.taker_collateral_account.to_account_info(), authority: ctx.accounts.vault_authority.to_account_info() };
+    token::transfer(CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_transfer, &[&seeds[..]]), bought)?;
+
+    auction.lot -= bought;
+    auction.tab -= paid;
+    auction.debt -= burned;
+    let (mut collateral_returned, mut shortfall) = (0, 0);
+    if auction.tab == 0 {
+        collateral_returned = auction.lot;
+        let position = &mut ctx.accounts.position;
+        position.collateral_amount = position.collateral_amount.checked_add(collateral_returned).ok_or(CustomErrorCode::MathOverflow)?;
+        position.last_updated = now;
+        auction.lot = 0;
+    } else if auction.lot == 0 {
+        shortfall = auction.debt;
+        let system_debt = &mut ctx.accounts.system_debt;
+        system_debt.bump = ctx.bumps.system_debt;
+        system_debt.recognize(shortfall)?;
+        emit!(BadDebtRecognized { position: auction.position, collateral_mint: auction.collateral_mint, amount: shortfall, bad_debt: system_debt.bad_debt });
+    }
+    emit!(AuctionTaken {
+        auction: auction.key(),
+        taker: ctx.accounts.taker.key(),
+        price,
+        collateral_bought: bought,
+        paid,
+        lot: auction.lot,
+        tab: auction.tab,
+        collateral_returned,
+        shortfall,
+    });
+    if auction.lot == 0 {
+        auction.close(ctx.accounts.keeper.to_account_info())?;
+    }
     Ok(())
 }
 
-// --- Governance ---
 #[derive(Accounts)]
-pub struct TogglePause<'info> {
-    #[account(mut, seeds = [b"global_state"], bump = global_state.bump)]
+pub struct Redo<'info> {
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    #[account(mut, seeds = [b"auction", auction.position.as_ref()], bump = auction.bump)]
+    pub auction: Account<'info, Auction>,
+    #[account(seeds = [b"collateral", auction.collateral_mint.as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Account<'info, CollateralConfig>,
+    #[account(seeds = [b"price_cache", auction.collateral_mint.as_ref()], bump = price_cache.bump)]
+    pub price_cache: Account<'info, PriceCache>,
+    #[account(seeds = [b"mint"], bump)]
+    pub usdt_mint: Account<'info, Mint>,
+    #[account(mut, seeds = [b"surplus"], bump, token::mint = usdt_mint, token::authority = global_state)]
+    pub surplus_account: Account<'info, TokenAccount>,
+    #[account(init_if_needed, payer = keeper, associated_token::mint = usdt_mint, associated_token::authority = keeper)]
+    pub keeper_usdt_account: Account<'info, TokenAccount>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump)]
     pub global_state: Account<'info, GlobalState>,
-    pub admin: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Permissionless: restarts an auction that is past its tail or cusp from the current
+/// cached price.
+pub fn redo_handler(ctx: Context<Redo>) -> Result<()> {
+    if ctx.accounts.global_state.paused { return err!(CustomErrorCode::Paused); }
+    let now = Clock::get()?.unix_timestamp;
+    let params = ctx.accounts.collateral_config.auction;
+    let auction = &mut ctx.accounts.auction;
+    let current = auction_price(auction.top, now.saturating_sub(auction.start_time), params.duration_secs);
+    if !auction.needs_redo(current, &params, now) { return err!(CustomErrorCode::AuctionNotStale); }
+    let price = ctx.accounts.price_cache.liquidation_price(now)?;
+    auction.reset(price, &params, now)?;
+
+    let incentive = pay_keeper(&ctx.accounts.surplus_account, &ctx.accounts.keeper_usdt_account, &ctx.accounts.global_state, &ctx.accounts.token_program, params.incentive(auction.tab)?)?;
+    emit!(AuctionRedone { auction: auction.key(), keeper: ctx.accounts.keeper.key(), price, top: auction.top, incentive });
+    Ok(())
 }
+
 #[derive(Accounts)]
-pub struct ToggleFreeze<'info> {
+pub struct SetAuctionParams<'info> {
+    #[account(mut, seeds = [b"collateral", collateral_config.collateral_mint.as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Account<'info, CollateralConfig>,
     #[account(seeds = [b"global_state"], bump = global_state.bump)]
     pub global_state: Account<'info, GlobalState>,
-    #[account(mut)]
-    pub position: Account<'info, Position>,
-    pub admin: Signer<'info>,
+    #[account(seeds = [b"roles"], bump = roles.bump, constraint = roles.is_authorized(Role::RiskManager, &global_state, &authority.key()) @ CustomErrorCode::Unauthorized)]
+    pub roles: Account<'info, Roles>,
+    pub authority: Signer<'info>,
 }
-pub fn toggle_pause_handler(ctx: Context<TogglePause>, paused: bool) -> Result<()> {
-    ctx.accounts.global_state.paused = paused;
+
+#[derive(Accounts)]
+pub struct SetLiquidationParams<'info> {
+    #[account(mut, seeds = [b"collateral", collateral_config.collateral_mint.as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Account<'info, CollateralConfig>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(seeds = [b"roles"], bump = roles.bump, constraint = roles.is_authorized(Role::RiskManager, &global_state, &authority.key()) @ CustomErrorCode::Unauthorized)]
+    pub roles: Account<'info, Roles>,
+    pub authority: Signer<'info>,
+}
+
+pub fn set_liquidation_params_handler(ctx: Context<SetLiquidationParams>, close_factor_bps: u64, dust: u64, target_cr: u64) -> Result<()> {
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    let config = &mut ctx.accounts.collateral_config;
+    config.validate_liquidation_params(close_factor_bps, target_cr)?;
+    let old = config.params();
+    if update_collateral_params(config, &ctx.accounts.global_state, CollateralParams { close_factor_bps, dust, target_cr, ..old })? {
+        emit!(LiquidationParamsUpdated {
+            collateral_mint: config.collateral_mint,
+            old_close_factor_bps: old.close_factor_bps,
+            old_dust: old.dust,
+            old_target_cr: old.target_cr,
+            close_factor_bps,
+            dust,
+            target_cr,
+        });
+    }
     Ok(())
 }
-pub fn toggle_freeze_handler(ctx: Context<ToggleFreeze>, frozen: bool) -> Result<()> {
-    ctx.accounts.position.is_frozen = frozen;
+
+pub fn set_auction_params_handler(ctx: Context<SetAuctionParams>, params: AuctionParams) -> Result<()> {
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    params.validate()?;
+    let config = &mut ctx.accounts.collateral_config;
+    let old = config.params();
+    if update_collateral_params(config, &ctx.accounts.global_state, CollateralParams { auction: params, ..old })? {
+        emit!(AuctionParamsUpdated { collateral_mint: config.collateral_mint, old_params: old.auction, new_params: params });
+    }
     Ok(())
 }
 
-// --- PSM ---
+/// Applies `params` to `config` at once and returns true, unless they loosen its current
+/// set (see `CollateralParams::loosens`); then they are queued behind the timelock delay
+/// instead. Applying drops any queued change.
+fn update_collateral_params(config: &mut CollateralConfig, global_state: &GlobalState, params: CollateralParams) -> Result<bool> {
+    if params.loosens(&config.params()) {
+        let now = Clock::get()?.unix_timestamp;
+        let eta = now.checked_add(global_state.timelock_delay_at(now)).ok_or(CustomErrorCode::MathOverflow)?;
+        config.pending_params = params;
+        config.pending_params_eta = eta;
+        emit!(CollateralParamsQueued { collateral_mint: config.collateral_mint, params, eta });
+        return Ok(false);
+    }
+    config.set_params(params);
+    Ok(true)
+}
+
 #[derive(Accounts)]
-pub struct ConfigurePsm<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
+pub struct ApplyCollateralParams<'info> {
+    #[account(mut, seeds = [b"collateral", collateral_config.collateral_mint.as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Account<'info, CollateralConfig>,
     #[account(seeds = [b"global_state"], bump = global_state.bump)]
     pub global_state: Account<'info, GlobalState>,
-    pub token_mint: Account<'info, Mint>,
-    /// CHECK: Oracle price feed account
-    pub oracle: AccountInfo<'info>,
-    #[account(init, payer = admin, seeds = [b"psm", token_mint.key().as_ref()], bump, space = 8 + 32 + 32 + 8 + 8 + 1 + 32)]
-    pub psm_config: Account<'info, PsmConfig>,
-    #[account(init, payer = admin, seeds = [b"psm_vault", token_mint.key().as_ref()], bump, token::mint = token_mint, token::authority = psm_authority)]
-    pub psm_vault: Account<'info, TokenAccount>,
-    /// CHECK: Dedicated authority for PSM vaults
-    #[account(seeds = [b"psm_authority"], bump)]
-    pub psm_authority: AccountInfo<'info>,
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
 }
-#[account]
-pub struct PsmConfig { pub token_mint: Pubkey, pub vault: Pubkey, pub total_minted: u64, pub fee_basis_points: u64, pub bump: u8, pub oracle: Pubkey }
+
+/// Permissionless: applies queued collateral params once their timelock has passed.
+pub fn apply_collateral_params_handler(ctx: Context<ApplyCollateralParams>) -> Result<()> {
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    let config = &mut ctx.accounts.collateral_config;
+    if config.pending_params_eta == 0 { return err!(CustomErrorCode::NoPendingParamsChange); }
+    if Clock::get()?.unix_timestamp < config.pending_params_eta { return err!(CustomErrorCode::TimelockNotElapsed); }
+    let params = config.pending_params;
+    // LTR or the penalty may have changed through `execute_change` in the meantime.
+    config.validate_liquidation_params(params.close_factor_bps, params.target_cr)?;
+    let old_params = config.set_params(params);
+    emit!(CollateralParamsUpdated { collateral_mint: config.collateral_mint, old_params, new_params: params });
+    Ok(())
+}
+
+// --- Bad Debt ---
+// Debt the collateral could not repay stays in circulation as USDT. It is written off the
+// position into `SystemDebt` and covered by burning USDT from surplus, then the backstop.
+
 #[derive(Accounts)]
-pub struct SwapUsdcToUsdt<'info> {
+pub struct RecognizeBadDebt<'info> {
     #[account(mut)]
-    pub user: Signer<'info>,
-    #[account(mut, seeds = [b"psm", token_mint.key().as_ref()], bump = psm_config.bump)]
-    pub psm_config: Account<'info, PsmConfig>,
-    pub token_mint: Account<'info, Mint>,
-    /// CHECK: Oracle price feed account, must match config
-    #[account(address = psm_config.oracle @ CustomErrorCode::InvalidOracle)]
-    pub oracle: AccountInfo<'info>,
-    #[account(mut, seeds = [b"psm_vault", token_mint.key().as_ref()], bump)]
-    pub psm_vault: Account<'info, TokenAccount>,
-    #[account(mut, associated_token::mint = token_mint, associated_token::authority = user)]
-    pub user_token_account: Account<'info, TokenAccount>,
+    pub payer: Signer<'info>,
+    #[account(mut, seeds = [b"position", position.owner.as_ref(), position.collateral_mint.as_ref()], bump = position.bump)]
+    pub position: Account<'info, Position>,
+    #[account(mut, seeds = [b"collateral", position.collateral_mint.as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Account<'info, CollateralConfig>,
+    #[account(init_if_needed, payer = payer, space = SystemDebt::LEN, seeds = [b"system_debt"], bump)]
+    pub system_debt: Account<'info, SystemDebt>,
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Permissionless: writes the debt of a position with no collateral left off into the
+/// bad-debt ledger.
+pub fn recognize_bad_debt_handler(ctx: Context<RecognizeBadDebt>) -> Result<()> {
+    if ctx.accounts.global_state.paused { return err!(CustomErrorCode::Paused); }
+    let now = Clock::get()?.unix_timestamp;
+    let config = &mut ctx.accounts.collateral_config;
+    config.accrue(now)?;
+    let position = &mut ctx.accounts.position;
+    let debt = config.debt_of(position.normalized_debt)?;
+    if position.collateral_amount != 0 || debt == 0 { return err!(CustomErrorCode::NoBadDebt); }
+    config.remove_debt(position, debt)?;
+    config.sync_total_debt(&mut ctx.accounts.global_state)?;
+    position.last_updated = now;
+
+    let system_debt = &mut ctx.accounts.system_debt;
+    system_debt.bump = ctx.bumps.system_debt;
+    system_debt.recognize(debt)?;
+    emit!(BadDebtRecognized { position: position.key(), collateral_mint: position.collateral_mint, amount: debt, bad_debt: system_debt.bad_debt });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CoverBadDebt<'info> {
+    #[account(mut, seeds = [b"system_debt"], bump = system_debt.bump)]
+    pub system_debt: Account<'info, SystemDebt>,
     #[account(mut, seeds = [b"mint"], bump)]
     pub usdt_mint: Account<'info, Mint>,
-    #[account(
-        init_if_needed,
-        payer = user,
-        associated_token::mint = usdt_mint,
-        associated_token::authority = user
-    )]
-    pub user_usdt_account: Account<'info, TokenAccount>,
-    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    #[account(mut, seeds = [b"surplus"], bump, token::mint = usdt_mint, token::authority = global_state)]
+    pub surplus_account: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"backstop"], bump, token::mint = usdt_mint, token::authority = global_state)]
+    pub backstop_account: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump)]
     pub global_state: Account<'info, GlobalState>,
-    /// CHECK: PSM Authority
-    #[account(seeds = [b"psm_authority"], bump)]
-    pub psm_authority: AccountInfo<'info>,
     pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
-    pub system_program: Program<'info, System>,
 }
+
+/// Permissionless: burns USDT from surplus, then from the backstop up to its limit, against
+/// outstanding bad debt.
+pub fn cover_bad_debt_handler(ctx: Context<CoverBadDebt>) -> Result<()> {
+    if ctx.accounts.global_state.paused { return err!(CustomErrorCode::Paused); }
+    let system_debt = &mut ctx.accounts.system_debt;
+    let (from_surplus, from_backstop) = system_debt.cover(ctx.accounts.surplus_account.amount, ctx.accounts.backstop_account.amount);
+    // Rounding CDP supply never minted is covered without burning; it stays in the backstop first.
+    let burnable = ctx.accounts.global_state.record_debt_burn(from_surplus + from_backstop)?;
+    let burn_surplus = from_surplus.min(burnable);
+    let seeds = &[b"global_state".as_ref(), &[ctx.accounts.global_state.bump]];
+    for (account, amount) in [(&ctx.accounts.surplus_account, burn_surplus), (&ctx.accounts.backstop_account, burnable - burn_surplus)] {
+        if amount == 0 { continue; }
+        let cpi_accounts = Burn { mint: ctx.accounts.usdt_mint.to_account_info(), from: account.to_account_info(), authority: ctx.accounts.global_state.to_account_info() };
+        token::burn(CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, &[&seeds[..]]), amount)?;
+    }
+    emit!(BadDebtCovered { from_surplus, from_backstop, bad_debt: system_debt.bad_debt, backstop_limit: system_debt.backstop_limit });
+    Ok(())
+}
+
 #[derive(Accounts)]
-pub struct SwapUsdtToUsdc<'info> {
+pub struct SetBackstopLimit<'info> {
+    #[account(init_if_needed, payer = admin, space = SystemDebt::LEN, seeds = [b"system_debt"], bump)]
+    pub system_debt: Account<'info, SystemDebt>,
+    #[account(seeds = [b"mint"], bump)]
+    pub usdt_mint: Account<'info, Mint>,
+    #[account(init_if_needed, payer = admin, seeds = [b"backstop"], bump, token::mint = usdt_mint, token::authority = global_state)]
+    pub backstop_account: Account<'info, TokenAccount>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump, has_one = admin @ CustomErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
     #[account(mut)]
-    pub user: Signer<'info>,
-    #[account(mut, seeds = [b"psm", token_mint.key().as_ref()], bump = psm_config.bump)]
+    pub admin: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Governance sets how much of the backstop account (funded by plain USDT transfers) may be
+/// burned against bad debt once surplus runs out.
+pub fn set_backstop_limit_handler(ctx: Context<SetBackstopLimit>, limit: u64) -> Result<()> {
+    let system_debt = &mut ctx.accounts.system_debt;
+    system_debt.bump = ctx.bumps.system_debt;
+    let old_limit = system_debt.backstop_limit;
+    system_debt.backstop_limit = limit;
+    emit!(BackstopLimitUpdated { old_limit, new_limit: limit });
+    Ok(())
+}
+
+// --- Treasury ---
+#[derive(Accounts)]
+pub struct ConfigureTreasury<'info> {
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(seeds = [b"mint"], bump)]
+    pub usdt_mint: Account<'info, Mint>,
+    #[account(token::mint = usdt_mint)]
+    pub fee_recipient: Option<Account<'info, TokenAccount>>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump, has_one = admin @ CustomErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    pub admin: Signer<'info>,
+}
+
+/// Sets the revenue split and withdrawal limits. A fee recipient account is required while
+/// it has a share. Changes that let more out (see `TreasuryParams::loosens`), or point the
+/// fee share at a new recipient, are queued behind the timelock delay instead; anything
+/// else applies at once and drops a queued change.
+pub fn configure_treasury_handler(ctx: Context<ConfigureTreasury>, params: TreasuryParams) -> Result<()> {
+    params.validate()?;
+    let fee_recipient = ctx.accounts.fee_recipient.as_ref().map(|a| a.key()).unwrap_or_default();
+    if params.fee_recipient_bps > 0 && fee_recipient == Pubkey::default() { return err!(CustomErrorCode::MissingFeeRecipient); }
+    let treasury = &mut ctx.accounts.treasury;
+    let redirects = params.fee_recipient_bps > 0 && fee_recipient != treasury.fee_recipient;
+    if params.loosens(&treasury.params) || redirects {
+        let now = Clock::get()?.unix_timestamp;
+        let eta = now.checked_add(ctx.accounts.global_state.timelock_delay_at(now)).ok_or(CustomErrorCode::MathOverflow)?;
+        treasury.pending_params = params;
+        treasury.pending_fee_recipient = fee_recipient;
+        treasury.pending_eta = eta;
+        emit!(TreasuryParamsQueued { params, fee_recipient, eta });
+        return Ok(());
+    }
+    let (old_params, old_fee_recipient) = treasury.set_params(params, fee_recipient);
+    emit!(TreasuryParamsUpdated { old_params, new_params: params, old_fee_recipient, fee_recipient });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ApplyTreasuryParams<'info> {
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+}
+
+/// Permissionless: applies queued treasury params once their timelock has passed.
+pub fn apply_treasury_params_handler(ctx: Context<ApplyTreasuryParams>) -> Result<()> {
+    let treasury = &mut ctx.accounts.treasury;
+    if treasury.pending_eta == 0 { return err!(CustomErrorCode::NoPendingTreasuryChange); }
+    if Clock::get()?.unix_timestamp < treasury.pending_eta { return err!(CustomErrorCode::TimelockNotElapsed); }
+    let (params, fee_recipient) = (treasury.pending_params, treasury.pending_fee_recipient);
+    let (old_params, old_fee_recipient) = treasury.set_params(params, fee_recipient);
+    emit!(TreasuryParamsUpdated { old_params, new_params: params, old_fee_recipient, fee_recipient });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct WithdrawSurplus<'info> {
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(init_if_needed, payer = admin, space = SystemDebt::LEN, seeds = [b"system_debt"], bump)]
+    pub system_debt: Account<'info, SystemDebt>,
+    #[account(seeds = [b"mint"], bump)]
+    pub usdt_mint: Account<'info, Mint>,
+    #[account(mut, seeds = [b"surplus"], bump, token::mint = usdt_mint, token::authority = global_state)]
+    pub surplus_account: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = usdt_mint)]
+    pub destination: Account<'info, TokenAccount>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump, has_one = admin @ CustomErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Governance withdrawal from surplus, rate-limited and never below the buffer floor plus
+/// outstanding bad debt.
+pub fn withdraw_surplus_handler(ctx: Context<WithdrawSurplus>, amount: u64) -> Result<()> {
+    // Surplus is left out of the redeemable supply at settlement, so it can't leave afterwards.
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    let now = Clock::get()?.unix_timestamp;
+    ctx.accounts.system_debt.bump = ctx.bumps.system_debt;
+    let bad_debt = ctx.accounts.system_debt.bad_debt;
+    let surplus = ctx.accounts.surplus_account.amount;
+    ctx.accounts.treasury.record_withdrawal(amount, surplus, bad_debt, now)?;
+    let seeds = &[b"global_state".as_ref(), &[ctx.accounts.global_state.bump]];
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.surplus_account.to_account_info(),
+        to: ctx.accounts.destination.to_account_info(),
+        authority: ctx.accounts.global_state.to_account_info(),
+    };
+    token::transfer(CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, &[&seeds[..]]), amount)?;
+    emit!(SurplusWithdrawn { destination: ctx.accounts.destination.key(), amount, surplus: surplus - amount, bad_debt });
+    Ok(())
+}
+
+// --- Stability Fee ---
+#[derive(Accounts)]
+pub struct SetStabilityFee<'info> {
+    #[account(mut, seeds = [b"collateral", collateral_config.collateral_mint.as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Account<'info, CollateralConfig>,
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(seeds = [b"roles"], bump = roles.bump, constraint = roles.is_authorized(Role::RiskManager, &global_state, &authority.key()) @ CustomErrorCode::Unauthorized)]
+    pub roles: Account<'info, Roles>,
+    pub authority: Signer<'info>,
+}
+
+/// Applies at once in both directions. The fee is capped at `MAX_STABILITY_FEE_BPS` a year
+/// and only accrues while it stays set, so a hostile change costs borrowers a sliver of the
+/// cap before it is reverted, and it never makes a position liquidatable by itself.
+pub fn set_stability_fee_handler(ctx: Context<SetStabilityFee>, fee_bps: u64) -> Result<()> {
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    if fee_bps > CollateralConfig::MAX_STABILITY_FEE_BPS { return err!(CustomErrorCode::InvalidStabilityFee); }
+    let config = &mut ctx.accounts.collateral_config;
+    // Settle the old rate up to now before switching.
+    config.accrue(Clock::get()?.unix_timestamp)?;
+    config.sync_total_debt(&mut ctx.accounts.global_state)?;
+    let old_fee_bps = config.stability_fee_bps;
+    config.stability_fee_bps = fee_bps;
+    emit!(StabilityFeeUpdated { collateral_mint: config.collateral_mint, old_fee_bps, new_fee_bps: fee_bps });
+    Ok(())
+}
+
+/// Mints `config`'s accrued fees into surplus and returns the amount.
+fn collect_fees<'info>(
+    config: &mut CollateralConfig,
+    global_state: &mut Account<'info, GlobalState>,
+    usdt_mint: &Account<'info, Mint>,
+    surplus_account: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+) -> Result<u64> {
+    let amount = config.unclaimed_fees;
+    config.unclaimed_fees = 0;
+    global_state.record_mint(SupplySource::Cdp, amount)?;
+    if amount > 0 {
+        let seeds = &[b"global_state".as_ref(), &[global_state.bump]];
+        let cpi_accounts = MintTo {
+            mint: usdt_mint.to_account_info(),
+            to: surplus_account.to_account_info(),
+            authority: global_state.to_account_info(),
+        };
+        token::mint_to(CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, &[&seeds[..]]), amount)?;
+        emit!(StabilityFeesCollected { collateral_mint: config.collateral_mint, amount });
+    }
+    Ok(amount)
+}
+
+// Permissionless: mints accrued stability fees into the protocol surplus account.
+#[derive(Accounts)]
+pub struct CollectStabilityFees<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, seeds = [b"collateral", collateral_config.collateral_mint.as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Account<'info, CollateralConfig>,
+    #[account(mut, seeds = [b"mint"], bump)]
+    pub usdt_mint: Account<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [b"surplus"],
+        bump,
+        token::mint = usdt_mint,
+        token::authority = global_state,
+    )]
+    pub surplus_account: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn collect_stability_fees_handler(ctx: Context<CollectStabilityFees>) -> Result<()> {
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    let config = &mut ctx.accounts.collateral_config;
+    config.accrue(Clock::get()?.unix_timestamp)?;
+    config.sync_total_debt(&mut ctx.accounts.global_state)?;
+    collect_fees(config, &mut ctx.accounts.global_state, &ctx.accounts.usdt_mint, &ctx.accounts.surplus_account, &ctx.accounts.token_program)?;
+    Ok(())
+}
+
+// --- Debt Ceilings ---
+#[derive(Accounts)]
+pub struct SetDebtCeiling<'info> {
+    #[account(mut, seeds = [b"collateral", collateral_config.collateral_mint.as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Account<'info, CollateralConfig>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(seeds = [b"roles"], bump = roles.bump, constraint = roles.is_authorized(Role::RiskManager, &global_state, &authority.key()) @ CustomErrorCode::Unauthorized)]
+    pub roles: Account<'info, Roles>,
+    pub authority: Signer<'info>,
+}
+
+/// Lowering the ceiling applies at once; raising it waits out the timelock.
+pub fn set_debt_ceiling_handler(ctx: Context<SetDebtCeiling>, ceiling: u64) -> Result<()> {
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    let config = &mut ctx.accounts.collateral_config;
+    let old = config.params();
+    if update_collateral_params(config, &ctx.accounts.global_state, CollateralParams { debt_ceiling: ceiling, ..old })? {
+        emit!(DebtCeilingUpdated { collateral_mint: config.collateral_mint, old_ceiling: old.debt_ceiling, new_ceiling: ceiling });
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetGlobalDebtCeiling<'info> {
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(seeds = [b"roles"], bump = roles.bump, constraint = roles.is_authorized(Role::RiskManager, &global_state, &authority.key()) @ CustomErrorCode::Unauthorized)]
+    pub roles: Account<'info, Roles>,
+    pub authority: Signer<'info>,
+}
+
+/// Applies at once in both directions: every collateral's own ceiling is timelocked, so
+/// raising the global one can't let any collateral borrow past what it already allowed.
+pub fn set_global_debt_ceiling_handler(ctx: Context<SetGlobalDebtCeiling>, ceiling: u64) -> Result<()> {
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    let global_state = &mut ctx.accounts.global_state;
+    let old_ceiling = global_state.debt_ceiling;
+    global_state.debt_ceiling = ceiling;
+    emit!(GlobalDebtCeilingUpdated { old_ceiling, new_ceiling: ceiling });
+    Ok(())
+}
+
+// --- Oracle Params ---
+#[derive(Accounts)]
+pub struct SetOracleParams<'info> {
+    #[account(mut, seeds = [b"collateral", collateral_config.collateral_mint.as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Account<'info, CollateralConfig>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(seeds = [b"roles"], bump = roles.bump, constraint = roles.is_authorized(Role::RiskManager, &global_state, &authority.key()) @ CustomErrorCode::Unauthorized)]
+    pub roles: Account<'info, Roles>,
+    pub authority: Signer<'info>,
+}
+
+/// Tighter bounds apply at once; accepting staler or less certain prices waits out the
+/// timelock.
+pub fn set_oracle_params_handler(ctx: Context<SetOracleParams>, max_staleness_secs: u64, max_confidence_bps: u64) -> Result<()> {
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    validate_oracle_params(max_staleness_secs, max_confidence_bps)?;
+    let config = &mut ctx.accounts.collateral_config;
+    let old = config.params();
+    if update_collateral_params(config, &ctx.accounts.global_state, CollateralParams { max_staleness_secs, max_confidence_bps, ..old })? {
+        emit!(OracleParamsUpdated {
+            mint: config.collateral_mint,
+            old_max_staleness_secs: old.max_staleness_secs,
+            old_max_confidence_bps: old.max_confidence_bps,
+            max_staleness_secs,
+            max_confidence_bps,
+        });
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(feed_id: [u8; 32])]
+pub struct PostGovernancePrice<'info> {
+    #[account(init_if_needed, payer = authority, space = GovernancePrice::LEN, seeds = [b"governance_price", feed_id.as_ref()], bump)]
+    pub governance_price: Account<'info, GovernancePrice>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(seeds = [b"roles"], bump = roles.bump, constraint = roles.is_authorized(Role::OracleAdmin, &global_state, &authority.key()) @ CustomErrorCode::Unauthorized)]
+    pub roles: Account<'info, Roles>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn post_governance_price_handler(ctx: Context<PostGovernancePrice>, feed_id: [u8; 32], price: u64) -> Result<()> {
+    if price == 0 { return err!(CustomErrorCode::InvalidOracleParams); }
+    let posted = &mut ctx.accounts.governance_price;
+    let publish_time = Clock::get()?.unix_timestamp;
+    posted.feed_id = feed_id;
+    posted.price = price;
+    posted.publish_time = publish_time;
+    posted.bump = ctx.bumps.governance_price;
+    emit!(GovernancePricePosted { feed_id, price, publish_time });
+    Ok(())
+}
+
+// --- Price Cache ---
+#[derive(Accounts)]
+pub struct Poke<'info> {
+    #[account(mut, seeds = [b"price_cache", collateral_config.collateral_mint.as_ref()], bump = price_cache.bump)]
+    pub price_cache: Account<'info, PriceCache>,
+    #[account(seeds = [b"collateral", collateral_config.collateral_mint.as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Account<'info, CollateralConfig>,
+    /// CHECK: Oracle
+    #[account(address = collateral_config.oracle)]
+    pub oracle: AccountInfo<'info>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+/// Permissionless: anyone may roll the cache forward once its delay has passed. Pokes stop
+/// at global settlement, so the cached price can't move while collateral is being settled.
+pub fn poke_handler(ctx: Context<Poke>) -> Result<()> {
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    let price = collateral_price(&ctx.accounts.collateral_config, &ctx.accounts.oracle, ctx.remaining_accounts, PriceBias::Mid)?;
+    let cache = &mut ctx.accounts.price_cache;
+    cache.poke(price, Clock::get()?.unix_timestamp)?;
+    emit!(PriceCachePoked { collateral_mint: cache.collateral_mint, current_price: cache.current_price, next_price: cache.next_price });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetPriceCacheDelay<'info> {
+    #[account(mut, seeds = [b"price_cache", price_cache.collateral_mint.as_ref()], bump = price_cache.bump)]
+    pub price_cache: Account<'info, PriceCache>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(seeds = [b"roles"], bump = roles.bump, constraint = roles.is_authorized(Role::RiskManager, &global_state, &authority.key()) @ CustomErrorCode::Unauthorized)]
+    pub roles: Account<'info, Roles>,
+    pub authority: Signer<'info>,
+}
+
+/// Raising the delay applies at once. A shorter delay serves fresher ticks, so a reduction
+/// waits out the risk-parameter timelock like any other loosening change.
+pub fn set_price_cache_delay_handler(ctx: Context<SetPriceCacheDelay>, delay_secs: u64) -> Result<()> {
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    if !(MIN_PRICE_DELAY_SECS..=MAX_PRICE_DELAY_SECS).contains(&delay_secs) { return err!(CustomErrorCode::InvalidPriceDelay); }
+    let now = Clock::get()?.unix_timestamp;
+    let timelock_delay = ctx.accounts.global_state.timelock_delay_at(now);
+    let cache = &mut ctx.accounts.price_cache;
+    let old_delay_secs = cache.delay_at(now);
+    let effective_at = if delay_secs < old_delay_secs { now.checked_add(timelock_delay).ok_or(CustomErrorCode::MathOverflow)? } else { now };
+    cache.delay_secs = old_delay_secs.max(delay_secs);
+    (cache.pending_delay_secs, cache.delay_eta) = if delay_secs < old_delay_secs { (delay_secs, effective_at) } else { (0, 0) };
+    emit!(PriceCacheDelayUpdated { collateral_mint: cache.collateral_mint, old_delay_secs, new_delay_secs: delay_secs, effective_at });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct FreezePriceCache<'info> {
+    #[account(mut, seeds = [b"price_cache", price_cache.collateral_mint.as_ref()], bump = price_cache.bump)]
+    pub price_cache: Account<'info, PriceCache>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump, has_one = admin @ CustomErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    pub admin: Signer<'info>,
+}
+
+/// Governance override: halts pokes and liquidations while frozen.
+pub fn freeze_price_cache_handler(ctx: Context<FreezePriceCache>, frozen: bool) -> Result<()> {
+    let cache = &mut ctx.accounts.price_cache;
+    cache.set_frozen(frozen);
+    emit!(PriceCacheFreezeUpdated { collateral_mint: cache.collateral_mint, frozen, current_price: cache.current_price });
+    Ok(())
+}
+
+// --- Governance ---
+// Admin-only instructions carry `global_state` with `has_one = admin`. Operational
+// instructions instead check the `roles` account, where the admin implicitly holds
+// every role.
+#[derive(Accounts)]
+pub struct TogglePause<'info> {
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(seeds = [b"roles"], bump = roles.bump, constraint = roles.is_authorized(Role::Pauser, &global_state, &authority.key()) @ CustomErrorCode::Unauthorized)]
+    pub roles: Account<'info, Roles>,
+    pub authority: Signer<'info>,
+}
+#[derive(Accounts)]
+pub struct ToggleFreeze<'info> {
+    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(seeds = [b"roles"], bump = roles.bump, constraint = roles.is_authorized(Role::Compliance, &global_state, &authority.key()) @ CustomErrorCode::Unauthorized)]
+    pub roles: Account<'info, Roles>,
+    #[account(mut)]
+    pub position: Account<'info, Position>,
+    pub authority: Signer<'info>,
+}
+#[derive(Accounts)]
+pub struct ManageRole<'info> {
+    #[account(seeds = [b"global_state"], bump = global_state.bump, has_one = admin @ CustomErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(mut, seeds = [b"roles"], bump = roles.bump)]
+    pub roles: Account<'info, Roles>,
+    pub admin: Signer<'info>,
+}
+pub fn toggle_pause_handler(ctx: Context<TogglePause>, paused: bool) -> Result<()> {
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    let old_paused = ctx.accounts.global_state.paused;
+    ctx.accounts.global_state.paused = paused;
+    emit!(PauseUpdated { old_paused, new_paused: paused });
+    Ok(())
+}
+pub fn toggle_freeze_handler(ctx: Context<ToggleFreeze>, frozen: bool) -> Result<()> {
+    let position = &mut ctx.accounts.position;
+    let old_frozen = position.is_frozen;
+    position.is_frozen = frozen;
+    emit!(PositionFreezeUpdated { owner: position.owner, collateral_mint: position.collateral_mint, old_frozen, new_frozen: frozen });
+    Ok(())
+}
+#[derive(Accounts)]
+pub struct ProposeAdmin<'info> {
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump, has_one = admin @ CustomErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    pub admin: Signer<'info>,
+}
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.pending_admin != Pubkey::default() @ CustomErrorCode::NoPendingAdmin,
+        constraint = global_state.pending_admin == new_admin.key() @ CustomErrorCode::Unauthorized
+    )]
+    pub global_state: Account<'info, GlobalState>,
+    pub new_admin: Signer<'info>,
+}
+pub fn propose_admin_handler(ctx: Context<ProposeAdmin>, new_admin: Pubkey) -> Result<()> {
+    if new_admin == Pubkey::default() { return err!(CustomErrorCode::InvalidAdmin); }
+    let global_state = &mut ctx.accounts.global_state;
+    global_state.pending_admin = new_admin;
+    emit!(AdminTransferProposed { admin: global_state.admin, pending_admin: new_admin });
+    Ok(())
+}
+pub fn accept_admin_handler(ctx: Context<AcceptAdmin>) -> Result<()> {
+    let global_state = &mut ctx.accounts.global_state;
+    let previous_admin = global_state.admin;
+    global_state.admin = global_state.pending_admin;
+    global_state.pending_admin = Pubkey::default();
+    emit!(AdminTransferAccepted { previous_admin, new_admin: global_state.admin });
+    Ok(())
+}
+pub fn cancel_admin_transfer_handler(ctx: Context<ProposeAdmin>) -> Result<()> {
+    let global_state = &mut ctx.accounts.global_state;
+    if global_state.pending_admin == Pubkey::default() { return err!(CustomErrorCode::NoPendingAdmin); }
+    let cancelled_admin = global_state.pending_admin;
+    global_state.pending_admin = Pubkey::default();
+    emit!(AdminTransferCancelled { admin: global_state.admin, cancelled_admin });
+    Ok(())
+}
+pub fn grant_role_handler(ctx: Context<ManageRole>, role: Role, member: Pubkey) -> Result<()> {
+    let roles = &mut ctx.accounts.roles;
+    let previous = roles.holder(role);
+    roles.set_holder(role, member);
+    emit!(RoleGranted { role, member, previous });
+    Ok(())
+}
+pub fn revoke_role_handler(ctx: Context<ManageRole>, role: Role) -> Result<()> {
+    let roles = &mut ctx.accounts.roles;
+    let member = roles.holder(role);
+    roles.set_holder(role, Pubkey::default());
+    emit!(RoleRevoked { role, member });
+    Ok(())
+}
+
+// --- PSM ---
+#[derive(Accounts)]
+pub struct ConfigurePsm<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(seeds = [b"roles"], bump = roles.bump, constraint = roles.is_authorized(Role::RiskManager, &global_state, &authority.key()) @ CustomErrorCode::Unauthorized)]
+    pub roles: Account<'info, Roles>,
+    pub token_mint: Account<'info, Mint>,
+    /// CHECK: Validated as a price feed in the handler
+    pub oracle: AccountInfo<'info>,
+    #[account(init, payer = authority, seeds = [b"psm", token_mint.key().as_ref()], bump, space = PsmConfig::LEN)]
+    pub psm_config: Account<'info, PsmConfig>,
+    #[account(init, payer = authority, seeds = [b"psm_vault", token_mint.key().as_ref()], bump, token::mint = token_mint, token::authority = psm_authority)]
+    pub psm_vault: Account<'info, TokenAccount>,
+    #[account(seeds = [b"mint"], bump)]
+    pub usdt_mint: Account<'info, Mint>,
+    #[account(init, payer = authority, seeds = [b"psm_fee_vault", token_mint.key().as_ref()], bump, token::mint = usdt_mint, token::authority = psm_authority)]
+    pub psm_fee_vault: Account<'info, TokenAccount>,
+    /// CHECK: Dedicated authority for PSM vaults
+    #[account(seeds = [b"psm_authority"], bump)]
+    pub psm_authority: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+#[derive(Accounts)]
+pub struct SetPsmFees<'info> {
+    #[account(mut, seeds = [b"psm", psm_config.token_mint.as_ref()], bump = psm_config.bump)]
+    pub psm_config: Account<'info, PsmConfig>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(seeds = [b"roles"], bump = roles.bump, constraint = roles.is_authorized(Role::RiskManager, &global_state, &authority.key()) @ CustomErrorCode::Unauthorized)]
+    pub roles: Account<'info, Roles>,
+    pub authority: Signer<'info>,
+}
+#[derive(Accounts)]
+pub struct SetPsmLimits<'info> {
+    #[account(mut, seeds = [b"psm", psm_config.token_mint.as_ref()], bump = psm_config.bump)]
+    pub psm_config: Account<'info, PsmConfig>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(seeds = [b"roles"], bump = roles.bump, constraint = roles.is_authorized(Role::RiskManager, &global_state, &authority.key()) @ CustomErrorCode::Unauthorized)]
+    pub roles: Account<'info, Roles>,
+    pub authority: Signer<'info>,
+}
+#[derive(Accounts)]
+pub struct CollectPsmFees<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(seeds = [b"psm", psm_config.token_mint.as_ref()], bump = psm_config.bump)]
+    pub psm_config: Account<'info, PsmConfig>,
+    #[account(mut, address = psm_config.fee_vault)]
+    pub psm_fee_vault: Account<'info, TokenAccount>,
+    /// CHECK: PSM Authority
+    #[account(seeds = [b"psm_authority"], bump)]
+    pub psm_authority: AccountInfo<'info>,
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(seeds = [b"mint"], bump)]
+    pub usdt_mint: Account<'info, Mint>,
+    #[account(init_if_needed, payer = payer, seeds = [b"surplus"], bump, token::mint = usdt_mint, token::authority = global_state)]
+    pub surplus_account: Account<'info, TokenAccount>,
+    #[account(mut, address = treasury.fee_recipient @ CustomErrorCode::MissingFeeRecipient)]
+    pub fee_recipient_account: Option<Account<'info, TokenAccount>>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+#[derive(Accounts)]
+pub struct SwapUsdcToUsdt<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mut, seeds = [b"psm", token_mint.key().as_ref()], bump = psm_config.bump)]
+    pub psm_config: Account<'info, PsmConfig>,
+    pub token_mint: Account<'info, Mint>,
+    /// CHECK: Oracle price feed account, must match config
+    #[account(address = psm_config.oracle @ CustomErrorCode::InvalidOracle)]
+    pub oracle: AccountInfo<'info>,
+    #[account(mut, seeds = [b"psm_vault", token_mint.key().as_ref()], bump)]
+    pub psm_vault: Account<'info, TokenAccount>,
+    #[account(mut, associated_token::mint = token_mint, associated_token::authority = user)]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"mint"], bump)]
+    pub usdt_mint: Account<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = usdt_mint,
+        associated_token::authority = user
+    )]
+    pub user_usdt_account: Account<'info, TokenAccount>,
+    #[account(mut, address = psm_config.fee_vault)]
+    pub psm_fee_vault: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    /// CHECK: PSM Authority
+    #[account(seeds = [b"psm_authority"], bump)]
+    pub psm_authority: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+#[derive(Accounts)]
+pub struct SwapUsdtToUsdc<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mut, seeds = [b"psm", token_mint.key().as_ref()], bump = psm_config.bump)]
+    pub psm_config: Account<'info, PsmConfig>,
+    pub token_mint: Account<'info, Mint>,
+    /// CHECK: Oracle price feed account, must match config
+    #[account(address = psm_config.oracle @ CustomErrorCode::InvalidOracle)]
+    pub oracle: AccountInfo<'info>,
+    #[account(mut, seeds = [b"psm_vault", token_mint.key().as_ref()], bump)]
+    pub psm_vault: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = token_mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"mint"], bump)]
+    pub usdt_mint: Account<'info, Mint>,
+    #[account(mut, associated_token::mint = usdt_mint, associated_token::authority = user)]
+    pub user_usdt_account: Account<'info, TokenAccount>,
+    #[account(mut, address = psm_config.fee_vault)]
+    pub psm_fee_vault: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+    /// CHECK: PSM Authority
+    #[account(seeds = [b"psm_authority"], bump)]
+    pub psm_authority: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+pub fn configure_psm_handler(ctx: Context<ConfigurePsm>, oracle_source: OracleSource, tin_bps: u64, tout_bps: u64) -> Result<()> {
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    if tin_bps > PsmConfig::MAX_FEE_BPS || tout_bps > PsmConfig::MAX_FEE_BPS { return err!(CustomErrorCode::InvalidPsmFee); }
+    validate_primary_oracle(&ctx.accounts.oracle, &oracle_source)?;
+    let c = &mut ctx.accounts.psm_config; c.token_mint = ctx.accounts.token_mint.key(); c.vault = ctx.accounts.psm_vault.key(); c.tin_bps = tin_bps; c.tout_bps = tout_bps; c.bump = ctx.bumps.psm_config; c.oracle = ctx.accounts.oracle.key(); c.fee_vault = ctx.accounts.psm_fee_vault.key();
+    // Unbounded until governance sets limits
+    c.mint_cap = u64::MAX; c.window_secs = PsmConfig::DEFAULT_WINDOW_SECS; c.window_limit = u64::MAX; c.window_updated_at = Clock::get()?.unix_timestamp; c.min_price = 0; c.max_price = u64::MAX;
+    c.max_staleness_secs = DEFAULT_MAX_STALENESS_SECS; c.max_confidence_bps = DEFAULT_MAX_CONFIDENCE_BPS; c.oracle_source = oracle_source;
+    // Settlement needs every fee vault, so it counts them.
+    let g = &mut ctx.accounts.global_state; g.psm_count = g.psm_count.checked_add(1).ok_or(CustomErrorCode::MathOverflow)?;
+    emit!(PsmConfigured { token_mint: c.token_mint, oracle: c.oracle, oracle_source, tin_bps, tout_bps }); Ok(())
+}
+pub fn set_psm_fees_handler(ctx: Context<SetPsmFees>, tin_bps: u64, tout_bps: u64) -> Result<()> {
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    if tin_bps > PsmConfig::MAX_FEE_BPS || tout_bps > PsmConfig::MAX_FEE_BPS { return err!(CustomErrorCode::InvalidPsmFee); }
+    let c = &mut ctx.accounts.psm_config; let (old_tin_bps, old_tout_bps) = (c.tin_bps, c.tout_bps); c.tin_bps = tin_bps; c.tout_bps = tout_bps;
+    emit!(PsmFeesUpdated { token_mint: c.token_mint, old_tin_bps, old_tout_bps, tin_bps, tout_bps }); Ok(())
+}
+// Limits and oracle checks apply at once when tightened; loosening waits out the timelock.
+pub fn set_psm_limits_handler(ctx: Context<SetPsmLimits>, mint_cap: u64, window_secs: i64, window_limit: u64, min_price: u64, max_price: u64) -> Result<()> {
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    PsmConfig::validate_limits(window_secs, min_price, max_price)?;
+    let c = &mut ctx.accounts.psm_config; let old = c.params();
+    if update_psm_params(c, &ctx.accounts.global_state, PsmParams { mint_cap, window_secs, window_limit, min_price, max_price, ..old })? {
+        emit!(PsmLimitsUpdated { token_mint: c.token_mint, old_mint_cap: old.mint_cap, old_window_secs: old.window_secs, old_window_limit: old.window_limit, old_min_price: old.min_price, old_max_price: old.max_price, mint_cap, window_secs, window_limit, min_price, max_price });
+    }
+    Ok(())
+}
+pub fn set_psm_oracle_params_handler(ctx: Context<SetPsmLimits>, max_staleness_secs: u64, max_confidence_bps: u64) -> Result<()> {
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    validate_oracle_params(max_staleness_secs, max_confidence_bps)?;
+    let c = &mut ctx.accounts.psm_config; let old = c.params();
+    if update_psm_params(c, &ctx.accounts.global_state, PsmParams { max_staleness_secs, max_confidence_bps, ..old })? {
+        emit!(OracleParamsUpdated { mint: c.token_mint, old_max_staleness_secs: old.max_staleness_secs, old_max_confidence_bps: old.max_confidence_bps, max_staleness_secs, max_confidence_bps });
+    }
+    Ok(())
+}
+// Applies `params` at once and returns true, or queues them behind the timelock if they loosen the current set.
+fn update_psm_params(c: &mut PsmConfig, global_state: &GlobalState, params: PsmParams) -> Result<bool> {
+    if params.loosens(&c.params()) {
+        let now = Clock::get()?.unix_timestamp;
+        let eta = now.checked_add(global_state.timelock_delay_at(now)).ok_or(CustomErrorCode::MathOverflow)?;
+        c.pending_params = params; c.pending_params_eta = eta;
+        emit!(PsmParamsQueued { token_mint: c.token_mint, params, eta }); return Ok(false);
+    }
+    c.set_params(params); Ok(true)
+}
+#[derive(Accounts)]
+pub struct ApplyPsmParams<'info> {
+    #[account(mut, seeds = [b"psm", psm_config.token_mint.as_ref()], bump = psm_config.bump)]
+    pub psm_config: Account<'info, PsmConfig>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+}
+// Permissionless: applies queued PSM params once their timelock has passed.
+pub fn apply_psm_params_handler(ctx: Context<ApplyPsmParams>) -> Result<()> {
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    let c = &mut ctx.accounts.psm_config;
+    if c.pending_params_eta == 0 { return err!(CustomErrorCode::NoPendingParamsChange); }
+    if Clock::get()?.unix_timestamp < c.pending_params_eta { return err!(CustomErrorCode::TimelockNotElapsed); }
+    let params = c.pending_params; let old_params = c.set_params(params);
+    emit!(PsmParamsUpdated { token_mint: c.token_mint, old_params, new_params: params }); Ok(())
+}
+// Permissionless: sweeps the fee vault into the treasury split (no liquidator share).
+pub fn collect_psm_fees_handler(ctx: Context<CollectPsmFees>) -> Result<()> {
+    // Fee vaults are left out of the redeemable supply at settlement, so they stay put.
+    if ctx.accounts.global_state.settled { return err!(CustomErrorCode::SystemSettled); }
+    let amount = ctx.accounts.psm_fee_vault.amount;
+    let treasury = &mut ctx.accounts.treasury;
+    let (to_surplus, to_liquidator, to_fee_recipient) = treasury.split(amount, false);
+    treasury.total_psm_fees = treasury.total_psm_fees.checked_add(amount).ok_or(CustomErrorCode::MathOverflow)?;
+    let seeds = &[b"psm_authority".as_ref(), &[ctx.bumps.psm_authority]];
+    token::transfer(CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), Transfer { from: ctx.accounts.psm_fee_vault.to_account_info(), to: ctx.accounts.surplus_account.to_account_info(), authority: ctx.accounts.psm_authority.to_account_info() }, &[&seeds[..]]), to_surplus)?;
+    if to_fee_recipient > 0 {
+        let fee_recipient = ctx.accounts.fee_recipient_account.as_ref().ok_or(CustomErrorCode::MissingFeeRecipient)?;
+        token::transfer(CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), Transfer { from: ctx.accounts.psm_fee_vault.to_account_info(), to: fee_recipient.to_account_info(), authority: ctx.accounts.psm_authority.to_account_info() }, &[&seeds[..]]), to_fee_recipient)?;
+    }
+    emit!(RevenueCollected { source: RevenueSource::PsmFee, amount, to_surplus, to_liquidator, to_fee_recipient }); Ok(())
+}
+pub fn swap_to_usdt_handler(ctx: Context<SwapUsdcToUsdt>, amount: u64, min_amount_out: u64) -> Result<()> {
+    if ctx.accounts.global_state.paused { return err!(CustomErrorCode::Paused); }
+    let c = &ctx.accounts.psm_config;
+    let price = get_price(&ctx.accounts.oracle, &c.oracle_source, c.max_staleness_secs, c.max_confidence_bps, PriceBias::Collateral)?;
+    ctx.accounts.psm_config.check_price_band(price)?;
+    // Mint Amount = Deposit Amount * Price / 10^decimals (USDT and price both carry 6 decimals)
+    let mint_amount = u64::try_from(collateral_value(amount, price, ctx.accounts.token_mint.decimals)?).map_err(|_| CustomErrorCode::MathOverflow)?;
+    // tin is minted to the fee vault; the user receives the remainder
+    let fee = bps_fee(mint_amount, ctx.accounts.psm_config.tin_bps)?;
+    let user_amount = mint_amount.checked_sub(fee).ok_or(CustomErrorCode::MathOverflow)?;
+    if user_amount < min_amount_out { return err!(CustomErrorCode::SlippageExceeded); }
+
+    token::transfer(CpiContext::new(ctx.accounts.token_program.to_account_info(), Transfer { from: ctx.accounts.user_token_account.to_account_info(), to: ctx.accounts.psm_vault.to_account_info(), authority: ctx.accounts.user.to_account_info() }), amount)?;
+    let seeds = &[b"global_state".as_ref(), &[ctx.accounts.global_state.bump]];
+    token::mint_to(CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), MintTo { mint: ctx.accounts.usdt_mint.to_account_info(), to: ctx.accounts.user_usdt_account.to_account_info(), authority: ctx.accounts.global_state.to_account_info() }, &[&seeds[..]]), user_amount)?;
+    if fee > 0 {
+        token::mint_to(CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), MintTo { mint: ctx.accounts.usdt_mint.to_account_info(), to: ctx.accounts.psm_fee_vault.to_account_info(), authority: ctx.accounts.global_state.to_account_info() }, &[&seeds[..]]), fee)?;
+    }
+    ctx.accounts.global_state.record_mint(SupplySource::Psm, mint_amount)?;
+    ctx.accounts.psm_config.record_mint(mint_amount, amount, Clock::get()?.unix_timestamp)?;
+    ctx.accounts.psm_vault.reload()?;
+    ctx.accounts.psm_config.check_invariant(ctx.accounts.psm_vault.amount, ctx.accounts.global_state.psm_supply)?;
+    emit!(PsmSwapped { user: ctx.accounts.user.key(), token_mint: ctx.accounts.token_mint.key(), to_usdt: true, amount_in: amount, amount_out: user_amount, fee, price }); Ok(())
+}
+pub fn swap_to_usdc_handler(ctx: Context<SwapUsdtToUsdc>, amount: u64, min_amount_out: u64) -> Result<()> {
+    if ctx.accounts.global_state.paused { return err!(CustomErrorCode::Paused); }
+    let c = &ctx.accounts.psm_config;
+    let price = get_price(&ctx.accounts.oracle, &c.oracle_source, c.max_staleness_secs, c.max_confidence_bps, PriceBias::Debt)?;
+    // tout is moved to the fee vault; only the remainder is burned and redeemed
+    let fee = bps_fee(amount, ctx.accounts.psm_config.tout_bps)?;
+    let burn_amount = amount.checked_sub(fee).ok_or(CustomErrorCode::MathOverflow)?;
+    // Withdraw Amount = Burn Amount * 10^decimals / Price
+    let withdraw_amount = token_amount(burn_amount, price, ctx.accounts.token_mint.decimals)?;
+    if withdraw_amount < min_amount_out { return err!(CustomErrorCode::SlippageExceeded); }
+
+    if fee > 0 {
+        token::transfer(CpiContext::new(ctx.accounts.token_program.to_account_info(), Transfer { from: ctx.accounts.user_usdt_account.to_account_info(), to: ctx.accounts.psm_fee_vault.to_account_info(), authority: ctx.accounts.user.to_account_info() }), fee)?;
+    }
+    token::burn(CpiContext::new(ctx.accounts.token_program.to_account_info(), Burn { mint: ctx.accounts.usdt_mint.to_account_info(), from: ctx.accounts.user_usdt_account.to_account_info(), authority: ctx.accounts.user.to_account_info() }), burn_amount)?;
+    
+    let seeds = &[b"psm_authority".as_ref(), &[ctx.bumps.psm_authority]];
+    token::transfer(CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), Transfer { from: ctx.accounts.psm_vault.to_account_info(), to: ctx.accounts.user_token_account.to_account_info(), authority: ctx.accounts.psm_authority.to_account_info() }, &[&seeds[..]]), withdraw_amount)?;
+    ctx.accounts.global_state.record_burn(SupplySource::Psm, burn_amount)?;
+    ctx.accounts.psm_config.record_redeem(burn_amount, withdraw_amount, Clock::get()?.unix_timestamp)?;
+    ctx.accounts.psm_vault.reload()?;
+    ctx.accounts.psm_config.check_invariant(ctx.accounts.psm_vault.amount, ctx.accounts.global_state.psm_supply)?;
+    emit!(PsmSwapped { user: ctx.accounts.user.key(), token_mint: ctx.accounts.token_mint.key(), to_usdt: false, amount_in: amount, amount_out: withdraw_amount, fee, price }); Ok(())
+}
+
+// --- Solvency ---
+#[derive(Accounts)]
+pub struct CheckSolvency<'info> {
+    #[account(seeds = [b"global_state"], bump = global_state.bump, has_one = usdt_mint)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(seeds = [b"mint"], bump)]
+    pub usdt_mint: Account<'info, Mint>,
+}
+
+// Permissionless: compares tracked CDP + PSM supply against the real USDT mint supply.
+pub fn check_solvency_handler(ctx: Context<CheckSolvency>) -> Result<()> {
+    let global_state = &ctx.accounts.global_state;
+    let mint_supply = ctx.accounts.usdt_mint.supply;
+    global_state.check_solvency(mint_supply)?;
+    emit!(SolvencyChecked {
+        total_supply: global_state.total_supply,
+        cdp_supply: global_state.cdp_supply,
+        psm_supply: global_state.psm_supply,
+        mint_supply,
+    });
+    Ok(())
+}
+
+// --- Global Settlement ---
+// A one-way shutdown. `global_settle` pauses the system for good, each collateral's cached
+// price is frozen, positions are closed at those prices with excess collateral returned, and
+// after the wait USDT holders redeem pro-rata shares of the collateral and PSM vaults.
+
+#[derive(Accounts)]
+pub struct GlobalSettle<'info> {
+    #[account(init, payer = admin, space = Settlement::LEN, seeds = [b"settlement"], bump)]
+    pub settlement: Account<'info, Settlement>,
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump, has_one = admin @ CustomErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Shuts the system down. `wait_secs` is how long keepers get to settle positions before
+/// the redeemable supply is fixed.
+pub fn global_settle_handler(ctx: Context<GlobalSettle>, wait_secs: i64) -> Result<()> {
+    if !(0..=MAX_SETTLEMENT_WAIT_SECS).contains(&wait_secs) { return err!(CustomErrorCode::InvalidSettlementWait); }
+    let now = Clock::get()?.unix_timestamp;
+    let settlement = &mut ctx.accounts.settlement;
+    settlement.settled_at = now;
+    settlement.wait_secs = wait_secs;
+    settlement.bump = ctx.bumps.settlement;
+    let global_state = &mut ctx.accounts.global_state;
+    global_state.paused = true;
+    global_state.settled = true;
+    emit!(GlobalSettled { settled_at: now, wait_secs });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SettleCollateral<'info> {
+    #[account(mut, seeds = [b"collateral", collateral_config.collateral_mint.as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Account<'info, CollateralConfig>,
+    #[account(mut, seeds = [b"price_cache", collateral_config.collateral_mint.as_ref()], bump = price_cache.bump)]
+    pub price_cache: Account<'info, PriceCache>,
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump, constraint = global_state.settled @ CustomErrorCode::NotSettled)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+/// Permissionless: freezes the collateral at its cached price and stops fee accrual. Pokes
+/// stopped at global settlement, so `current_price` is the last price liquidations used. It
+/// is taken even if the cache was frozen or has since gone stale, so neither can hold
+/// settlement up.
+pub fn settle_collateral_handler(ctx: Context<SettleCollateral>) -> Result<()> {
+    let config = &mut ctx.accounts.collateral_config;
+    if config.settlement_price != 0 { return err!(CustomErrorCode::CollateralAlreadySettled); }
+    let now = Clock::get()?.unix_timestamp;
+    let cache = &mut ctx.accounts.price_cache;
+    let price = cache.current_price;
+    if price == 0 { return err!(CustomErrorCode::PriceCacheNotReady); }
+    config.accrue(now)?;
+    config.sync_total_debt(&mut ctx.accounts.global_state)?;
+    config.stability_fee_bps = 0;
+    config.settlement_price = price;
+    cache.set_frozen(true);
+    emit!(CollateralSettled { collateral_mint: config.collateral_mint, price, total_debt: config.total_debt });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SettleAuction<'info> {
+    #[account(mut, seeds = [b"auction", auction.position.as_ref()], bump = auction.bump, has_one = keeper)]
+    pub auction: Account<'info, Auction>,
+    #[account(mut, address = auction.position)]
+    pub position: Account<'info, Position>,
+    /// CHECK: Receives the auction account's rent
+    #[account(mut)]
+    pub keeper: AccountInfo<'info>,
+    #[account(mut, seeds = [b"collateral", auction.collateral_mint.as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Account<'info, CollateralConfig>,
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump, constraint = global_state.settled @ CustomErrorCode::NotSettled)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+/// Permissionless: cancels a running auction, handing its lot and unpaid debt (without the
+/// penalty) back to the position so it settles like any other.
+pub fn settle_auction_handler(ctx: Context<SettleAuction>) -> Result<()> {
+    let config = &mut ctx.accounts.collateral_config;
+    if config.settlement_price == 0 { return err!(CustomErrorCode::CollateralNotSettled); }
+    let auction = &ctx.accounts.auction;
+    let position = &mut ctx.accounts.position;
+    position.collateral_amount = position.collateral_amount.checked_add(auction.lot).ok_or(CustomErrorCode::MathOverflow)?;
+    config.add_debt(position, auction.debt)?;
+    config.sync_total_debt(&mut ctx.accounts.global_state)?;
+    emit!(AuctionSettled { auction: auction.key(), position: position.key(), lot: auction.lot, debt: auction.debt });
+    ctx.accounts.auction.close(ctx.accounts.keeper.to_account_info())
+}
+
+#[derive(Accounts)]
+pub struct SettlePosition<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, seeds = [b"position", owner.key().as_ref(), collateral_mint.key().as_ref()], bump = position.bump, has_one = owner)]
+    pub position: Account<'info, Position>,
+    #[account(mut, seeds = [b"collateral", collateral_mint.key().as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Account<'info, CollateralConfig>,
+    #[account(mut, seeds = [b"vault", collateral_mint.key().as_ref()], bump, token::mint = collateral_mint, token::authority = vault_authority)]
+    pub vault_token_account: Account<'info, TokenAccount>,
+    /// CHECK: Vault authority PDA
+    #[account(seeds = [b"vault_authority"], bump)]
+    pub vault_authority: AccountInfo<'info>,
+    pub collateral_mint: Account<'info, Mint>,
+    /// CHECK: Position owner, who receives the excess collateral
+    pub owner: AccountInfo<'info>,
+    #[account(init_if_needed, payer = payer, associated_token::mint = collateral_mint, associated_token::authority = owner)]
+    pub owner_token_account: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump, constraint = global_state.settled @ CustomErrorCode::NotSettled)]
+    pub global_state: Account<'info, GlobalState>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Permissionless: cancels a position's debt against collateral at the frozen price and
+/// returns the excess to its owner. Underwater positions give up all their collateral. A
+/// position frozen by compliance is settled too, so redemptions see its collateral, but its
+/// excess stays in the position until it is unfrozen and settled again.
+pub fn settle_position_handler(ctx: Context<SettlePosition>) -> Result<()> {
+    let config = &mut ctx.accounts.collateral_config;
+    if config.settlement_price == 0 { return err!(CustomErrorCode::CollateralNotSettled); }
+    let position = &mut ctx.accounts.position;
+    let debt = config.debt_of(position.normalized_debt)?;
+    let (owed, excess) = settlement_split(position.collateral_amount, debt, config.settlement_price, ctx.accounts.collateral_mint.decimals)?;
+    config.remove_debt(position, debt)?;
+    config.sync_total_debt(&mut ctx.accounts.global_state)?;
+    config.settled_collateral = config.settled_collateral.checked_add(owed).ok_or(CustomErrorCode::MathOverflow)?;
+    let returned = if position.is_frozen { 0 } else { excess };
+    position.collateral_amount = excess - returned;
+    position.last_updated = Clock::get()?.unix_timestamp;
+
+    if returned > 0 {
+        let seeds = &[b"vault_authority".as_ref(), &[ctx.bumps.vault_authority]];
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.vault_token_account.to_account_info(),
+            to: ctx.accounts.owner_token_account.to_account_info(),
+            authority: ctx.accounts.vault_authority.to_account_info(),
+        };
+        token::transfer(CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, &[&seeds[..]]), returned)?;
+    }
+    emit!(PositionSettled { owner: position.owner, collateral_mint: position.collateral_mint, debt, collateral_owed: owed, collateral_returned: returned });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct FixSettlement<'info> {
+    #[account(mut, seeds = [b"settlement"], bump = settlement.bump)]
+    pub settlement: Account<'info, Settlement>,
+    #[account(seeds = [b"mint"], bump)]
+    pub usdt_mint: Account<'info, Mint>,
+    /// CHECK: The surplus token account, which may never have been created
+    #[account(seeds = [b"surplus"], bump)]
+    pub surplus_account: UncheckedAccount<'info>,
+    /// CHECK: The backstop token account, which may never have been created
+    #[account(seeds = [b"backstop"], bump)]
+    pub backstop_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+/// Balance of a protocol-owned token PDA, or 0 if it was never created.
+fn pda_balance(info: &AccountInfo) -> Result<u64> {
+    if info.data_is_empty() {
+        return Ok(0);
+    }
+    Ok(TokenAccount::try_deserialize(&mut &info.try_borrow_data()?[..])?.amount)
+}
+
+/// Permissionless, once the wait is over: fixes the USDT supply that redeems against the
+/// pools. Protocol-owned USDT that can never be redeemed is left out: surplus, the backstop
+/// and every PSM fee vault. Each PSM must be passed in `remaining_accounts` as a
+/// (`psm_config`, fee vault) pair, and all of them are required. Auctions hold no USDT,
+/// since takes burn and pay out directly.
+pub fn fix_settlement_handler(ctx: Context<FixSettlement>) -> Result<()> {
+    let surplus = pda_balance(&ctx.accounts.surplus_account)?;
+    let backstop = pda_balance(&ctx.accounts.backstop_account)?;
+    let pairs = ctx.remaining_accounts.chunks_exact(2);
+    if !pairs.remainder().is_empty() { return err!(CustomErrorCode::InvalidSettlementAccount); }
+    let mut psm_fees = 0u64;
+    let mut seen: Vec<Pubkey> = Vec::new();
+    for pair in pairs {
+        let (config_info, vault_info) = (&pair[0], &pair[1]);
+        if config_info.owner != &crate::ID || seen.contains(config_info.key) { return err!(CustomErrorCode::InvalidSettlementAccount); }
+        let config = PsmConfig::try_deserialize(&mut &config_info.try_borrow_data()?[..])?;
+        if vault_info.key != &config.fee_vault { return err!(CustomErrorCode::InvalidSettlementAccount); }
+        seen.push(*config_info.key);
+        psm_fees = psm_fees.checked_add(pda_balance(vault_info)?).ok_or(CustomErrorCode::MathOverflow)?;
+    }
+    if seen.len() != ctx.accounts.global_state.psm_count as usize { return err!(CustomErrorCode::MissingPsmFeeVault); }
+    let protocol_owned = surplus.saturating_add(backstop).saturating_add(psm_fees);
+    let redeemable_supply = ctx.accounts.usdt_mint.supply.saturating_sub(protocol_owned);
+    ctx.accounts.settlement.fix(redeemable_supply, Clock::get()?.unix_timestamp)?;
+    emit!(SettlementFixed { redeemable_supply, surplus, backstop, psm_fees });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct OpenCollateralPool<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(init, payer = payer, space = SettlementPool::LEN, seeds = [b"settlement_pool", vault_token_account.key().as_ref()], bump)]
+    pub pool: Account<'info, SettlementPool>,
+    #[account(seeds = [b"collateral", collateral_mint.key().as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Account<'info, CollateralConfig>,
+    #[account(seeds = [b"vault", collateral_mint.key().as_ref()], bump)]
+    pub vault_token_account: Account<'info, TokenAccount>,
+    pub collateral_mint: Account<'info, Mint>,
+    #[account(seeds = [b"settlement"], bump = settlement.bump, constraint = settlement.fixed @ CustomErrorCode::SettlementNotFixed)]
+    pub settlement: Account<'info, Settlement>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Permissionless: opens a collateral's redemption pool with the collateral taken from
+/// positions settled so far. Positions settled later grow the pool; see `cash_handler`.
+pub fn open_collateral_pool_handler(ctx: Context<OpenCollateralPool>) -> Result<()> {
+    let config = &ctx.accounts.collateral_config;
+    if config.settlement_price == 0 { return err!(CustomErrorCode::CollateralNotSettled); }
+    let amount = config.settled_collateral;
+    let pool = &mut ctx.accounts.pool;
+    pool.mint = config.collateral_mint;
+    pool.vault = ctx.accounts.vault_token_account.key();
+    pool.amount = amount;
+    pool.psm = false;
+    pool.bump = ctx.bumps.pool;
+    emit!(SettlementPoolOpened { mint: pool.mint, vault: pool.vault, amount, psm: false });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct OpenPsmPool<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(init, payer = payer, space = SettlementPool::LEN, seeds = [b"settlement_pool", psm_vault.key().as_ref()], bump)]
+    pub pool: Account<'info, SettlementPool>,
+    #[account(seeds = [b"psm", psm_config.token_mint.as_ref()], bump = psm_config.bump)]
     pub psm_config: Account<'info, PsmConfig>,
-    pub token_mint: Account<'info, Mint>,
-    /// CHECK: Oracle price feed account, must match config
-    #[account(address = psm_config.oracle @ CustomErrorCode::InvalidOracle)]
-    pub oracle: AccountInfo<'info>,
-    #[account(mut, seeds = [b"psm_vault", token_mint.key().as_ref()], bump)]
+    #[account(address = psm_config.vault)]
     pub psm_vault: Account<'info, TokenAccount>,
-    #[account(
-        init_if_needed,
-        payer = user,
-        associated_token::mint = token_mint,
-        associated_token::authority = user
-    )]
-    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(seeds = [b"settlement"], bump = settlement.bump, constraint = settlement.fixed @ CustomErrorCode::SettlementNotFixed)]
+    pub settlement: Account<'info, Settlement>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Permissionless: opens a PSM's redemption pool with all of its reserves.
+pub fn open_psm_pool_handler(ctx: Context<OpenPsmPool>) -> Result<()> {
+    let amount = ctx.accounts.psm_vault.amount;
+    let pool = &mut ctx.accounts.pool;
+    pool.mint = ctx.accounts.psm_config.token_mint;
+    pool.vault = ctx.accounts.psm_vault.key();
+    pool.amount = amount;
+    pool.psm = true;
+    pool.bump = ctx.bumps.pool;
+    emit!(SettlementPoolOpened { mint: pool.mint, vault: pool.vault, amount, psm: true });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Redeem<'info> {
+    #[account(mut)]
+    pub holder: Signer<'info>,
+    #[account(init_if_needed, payer = holder, space = Redemption::LEN, seeds = [b"redemption", holder.key().as_ref()], bump)]
+    pub redemption: Account<'info, Redemption>,
     #[account(mut, seeds = [b"mint"], bump)]
     pub usdt_mint: Account<'info, Mint>,
-    #[account(mut, associated_token::mint = usdt_mint, associated_token::authority = user)]
-    pub user_usdt_account: Account<'info, TokenAccount>,
-    #[account(seeds = [b"global_state"], bump = global_state.bump)]
+    #[account(mut, associated_token::mint = usdt_mint, associated_token::authority = holder)]
+    pub holder_usdt_account: Account<'info, TokenAccount>,
+    #[account(seeds = [b"settlement"], bump = settlement.bump, constraint = settlement.fixed @ CustomErrorCode::SettlementNotFixed)]
+    pub settlement: Account<'info, Settlement>,
+    #[account(mut, seeds = [b"global_state"], bump = global_state.bump)]
     pub global_state: Account<'info, GlobalState>,
-    /// CHECK: PSM Authority
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Burns `amount` USDT towards the holder's share of every settlement pool.
+pub fn redeem_handler(ctx: Context<Redeem>, amount: u64) -> Result<()> {
+    let cpi_accounts = Burn {
+        mint: ctx.accounts.usdt_mint.to_account_info(),
+        from: ctx.accounts.holder_usdt_account.to_account_info(),
+        authority: ctx.accounts.holder.to_account_info(),
+    };
+    token::burn(CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts), amount)?;
+    ctx.accounts.global_state.record_redemption_burn(amount)?;
+    let redemption = &mut ctx.accounts.redemption;
+    redemption.holder = ctx.accounts.holder.key();
+    redemption.burned = redemption.burned.checked_add(amount).ok_or(CustomErrorCode::MathOverflow)?;
+    redemption.bump = ctx.bumps.redemption;
+    emit!(UsdtRedeemed { holder: redemption.holder, amount, burned: redemption.burned });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Cash<'info> {
+    #[account(mut)]
+    pub holder: Signer<'info>,
+    #[account(seeds = [b"redemption", holder.key().as_ref()], bump = redemption.bump)]
+    pub redemption: Account<'info, Redemption>,
+    #[account(init_if_needed, payer = holder, space = RedemptionClaim::LEN, seeds = [b"redemption_claim", holder.key().as_ref(), pool.key().as_ref()], bump)]
+    pub claim: Account<'info, RedemptionClaim>,
+    #[account(mut, seeds = [b"settlement_pool", pool.vault.as_ref()], bump = pool.bump)]
+    pub pool: Account<'info, SettlementPool>,
+    /// Required for collateral pools, to pick up positions settled since the last cash
+    #[account(seeds = [b"collateral", pool.mint.as_ref()], bump = collateral_config.bump)]
+    pub collateral_config: Option<Account<'info, CollateralConfig>>,
+    #[account(mut, address = pool.vault)]
+    pub vault: Account<'info, TokenAccount>,
+    /// CHECK: Signs for collateral vaults
+    #[account(seeds = [b"vault_authority"], bump)]
+    pub vault_authority: AccountInfo<'info>,
+    /// CHECK: Signs for PSM vaults
     #[account(seeds = [b"psm_authority"], bump)]
     pub psm_authority: AccountInfo<'info>,
+    #[account(address = pool.mint)]
+    pub mint: Account<'info, Mint>,
+    #[account(init_if_needed, payer = holder, associated_token::mint = mint, associated_token::authority = holder)]
+    pub holder_token_account: Account<'info, TokenAccount>,
+    #[account(seeds = [b"settlement"], bump = settlement.bump)]
+    pub settlement: Account<'info, Settlement>,
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
-pub fn configure_psm_handler(ctx: Context<ConfigurePsm>, fee: u64) -> Result<()> {
-    let c = &mut ctx.accounts.psm_config; c.token_mint = ctx.accounts.token_mint.key(); c.vault = ctx.accounts.psm_vault.key(); c.fee_basis_points = fee; c.bump = ctx.bumps.psm_config; c.oracle = ctx.accounts.oracle.key(); Ok(())
+
+/// Pays out the holder's share of one pool for everything redeemed so far, less what was
+/// already cashed. A collateral pool first grows to the collateral settled so far, so
+/// holders who cashed early can come back for their share of later settlements.
+pub fn cash_handler(ctx: Context<Cash>) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    if !pool.psm {
+        let config = ctx.accounts.collateral_config.as_ref().ok_or(CustomErrorCode::InvalidSettlementAccount)?;
+        pool.amount = pool.amount.max(config.settled_collateral);
+    }
+    let owed = ctx.accounts.settlement.share(pool.amount, ctx.accounts.redemption.burned);
+    let claim = &mut ctx.accounts.claim;
+    claim.holder = ctx.accounts.holder.key();
+    claim.pool = ctx.accounts.pool.key();
+    claim.bump = ctx.bumps.claim;
+    let amount = owed.saturating_sub(claim.cashed);
+    claim.cashed = claim.cashed.max(owed);
+
+    if amount > 0 {
+        let (authority, seeds): (_, &[&[u8]]) = if ctx.accounts.pool.psm {
+            (ctx.accounts.psm_authority.to_account_info(), &[b"psm_authority", &[ctx.bumps.psm_authority]])
+        } else {
+            (ctx.accounts.vault_authority.to_account_info(), &[b"vault_authority", &[ctx.bumps.vault_authority]])
+        };
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.vault.to_account_info(),
+            to: ctx.accounts.holder_token_account.to_account_info(),
+            authority,
+        };
+        token::transfer(CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, &[seeds]), amount)?;
+    }
+    emit!(RedemptionCashed { holder: claim.holder, pool: claim.pool, amount });
+    Ok(())
+}
+
+// --- Multisig ---
+#[derive(Accounts)]
+pub struct CreateMultisig<'info> {
+    #[account(init, payer = payer, space = Multisig::LEN)]
+    pub multisig: Account<'info, Multisig>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn create_multisig_handler(ctx: Context<CreateMultisig>, signers: Vec<Pubkey>, threshold: u8) -> Result<()> {
+    Multisig::validate_config(&signers, threshold)?;
+    let multisig_key = ctx.accounts.multisig.key();
+    let (_, signer_bump) = Pubkey::find_program_address(&[b"multisig_signer", multisig_key.as_ref()], ctx.program_id);
+    let multisig = &mut ctx.accounts.multisig;
+    multisig.signers = signers;
+    multisig.threshold = threshold;
+    multisig.nonce = 0;
+    multisig.owner_set_seqno = 0;
+    multisig.signer_bump = signer_bump;
+    emit!(MultisigUpdated { multisig: multisig_key, signers: multisig.signers.clone(), threshold, owner_set_seqno: 0 });
+    Ok(())
 }
-pub fn swap_to_usdt_handler(ctx: Context<SwapUsdcToUsdt>, amount: u64) -> Result<()> {
-    if ctx.accounts.global_state.paused { return err!(CustomErrorCode::Paused); }
-    let price = utils::get_price(&ctx.accounts.oracle)?;
-    // Mint Amount = (Deposit Amount * Price) / 1_000_000 (since price is scaled by 1e6)
-    let mint_amount = (amount as u128).checked_mul(price as u128).unwrap().checked_div(1_000_000).unwrap() as u64;
 
-    token::transfer(CpiContext::new(ctx.accounts.token_program.to_account_info(), Transfer { from: ctx.accounts.user_token_account.to_account_info(), to: ctx.accounts.psm_vault.to_account_info(), authority: ctx.accounts.user.to_account_info() }), amount)?;
-    let seeds = &[b"global_state".as_ref(), &[ctx.accounts.global_state.bump]];
-    token::mint_to(CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), MintTo { mint: ctx.accounts.usdt_mint.to_account_info(), to: ctx.accounts.user_usdt_account.to_account_info(), authority: ctx.accounts.global_state.to_account_info() }, &[&seeds[..]]), mint_amount)?;
-    ctx.accounts.psm_config.total_minted += mint_amount; Ok(())
+// Only callable by the multisig itself, i.e. through an executed multisig transaction.
+#[derive(Accounts)]
+pub struct UpdateMultisig<'info> {
+    #[account(mut)]
+    pub multisig: Account<'info, Multisig>,
+    #[account(seeds = [b"multisig_signer", multisig.key().as_ref()], bump = multisig.signer_bump)]
+    pub multisig_signer: Signer<'info>,
 }
-pub fn swap_to_usdc_handler(ctx: Context<SwapUsdtToUsdc>, amount: u64) -> Result<()> {
-    if ctx.accounts.global_state.paused { return err!(CustomErrorCode::Paused); }
-    let price = utils::get_price(&ctx.accounts.oracle)?;
-    // Withdraw Amount = (Burn Amount * 1_000_000) / Price
-    let withdraw_amount = (amount as u128).checked_mul(1_000_000).unwrap().checked_div(price as u128).unwrap() as u64;
 
-    token::burn(CpiContext::new(ctx.accounts.token_program.to_account_info(), Burn { mint: ctx.accounts.usdt_mint.to_account_info(), from: ctx.accounts.user_usdt_account.to_account_info(), authority: ctx.accounts.user.to_account_info() }), amount)?;
-    
-    let seeds = &[b"psm_authority".as_ref(), &[ctx.bumps.psm_authority]];
-    token::transfer(CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), Transfer { from: ctx.accounts.psm_vault.to_account_info(), to: ctx.accounts.user_token_account.to_account_info(), authority: ctx.accounts.psm_authority.to_account_info() }, &[&seeds[..]]), withdraw_amount)?;
-    ctx.accounts.psm_config.total_minted = ctx.accounts.psm_config.total_minted.saturating_sub(amount); Ok(())
+pub fn update_multisig_handler(ctx: Context<UpdateMultisig>, signers: Vec<Pubkey>, threshold: u8) -> Result<()> {
+    Multisig::validate_config(&signers, threshold)?;
+    let multisig_key = ctx.accounts.multisig.key();
+    let multisig = &mut ctx.accounts.multisig;
+    multisig.signers = signers;
+    multisig.threshold = threshold;
+    multisig.owner_set_seqno = multisig.owner_set_seqno.checked_add(1).ok_or(CustomErrorCode::MathOverflow)?;
+    emit!(MultisigUpdated { multisig: multisig_key, signers: multisig.signers.clone(), threshold, owner_set_seqno: multisig.owner_set_seqno });
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(accounts: Vec<TxAccountMeta>, data: Vec<u8>)]
+pub struct ProposeMultisigTransaction<'info> {
+    #[account(mut)]
+    pub multisig: Account<'info, Multisig>,
+    #[account(
+        init,
+        payer = proposer,
+        seeds = [b"multisig_tx", multisig.key().as_ref(), &multisig.nonce.to_le_bytes()],
+        bump,
+        space = MultisigTransaction::space(accounts.len(), data.len(), multisig.signers.len())
+    )]
+    pub transaction: Account<'info, MultisigTransaction>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn propose_multisig_transaction_handler(
+    ctx: Context<ProposeMultisigTransaction>,
+    accounts: Vec<TxAccountMeta>,
+    data: Vec<u8>,
+) -> Result<()> {
+    let multisig = &mut ctx.accounts.multisig;
+    let proposer_index = multisig.signer_index(&ctx.accounts.proposer.key()).ok_or(CustomErrorCode::NotMultisigSigner)?;
+    let mut approvals = vec![false; multisig.signers.len()];
+    approvals[proposer_index] = true;
+
+    let tx = &mut ctx.accounts.transaction;
+    tx.multisig = multisig.key();
+    tx.index = multisig.nonce;
+    tx.accounts = accounts;
+    tx.data = data;
+    tx.approvals = approvals;
+    tx.executed = false;
+    tx.owner_set_seqno = multisig.owner_set_seqno;
+    tx.proposer = ctx.accounts.proposer.key();
+    tx.bump = ctx.bumps.transaction;
+    multisig.nonce = multisig.nonce.checked_add(1).ok_or(CustomErrorCode::MathOverflow)?;
+    emit!(MultisigTransactionProposed { multisig: tx.multisig, transaction: tx.key(), index: tx.index, proposer: tx.proposer });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ApproveMultisigTransaction<'info> {
+    pub multisig: Account<'info, Multisig>,
+    #[account(
+        mut,
+        has_one = multisig,
+        seeds = [b"multisig_tx", multisig.key().as_ref(), &transaction.index.to_le_bytes()],
+        bump = transaction.bump
+    )]
+    pub transaction: Account<'info, MultisigTransaction>,
+    pub approver: Signer<'info>,
+}
+
+pub fn approve_multisig_transaction_handler(ctx: Context<ApproveMultisigTransaction>) -> Result<()> {
+    let multisig = &ctx.accounts.multisig;
+    let tx = &mut ctx.accounts.transaction;
+    if tx.executed { return err!(CustomErrorCode::AlreadyExecuted); }
+    if tx.owner_set_seqno != multisig.owner_set_seqno { return err!(CustomErrorCode::MultisigChanged); }
+    let index = multisig.signer_index(&ctx.accounts.approver.key()).ok_or(CustomErrorCode::NotMultisigSigner)?;
+    tx.approvals[index] = true;
+    emit!(MultisigTransactionApproved {
+        multisig: multisig.key(),
+        transaction: tx.key(),
+        approver: ctx.accounts.approver.key(),
+        approvals: tx.approval_count() as u8,
+    });
+    Ok(())
+}
+
+// Remaining accounts must supply every account referenced by the stored instruction.
+#[derive(Accounts)]
+pub struct ExecuteMultisigTransaction<'info> {
+    pub multisig: Account<'info, Multisig>,
+    /// CHECK: PDA that signs the stored instruction on behalf of the multisig
+    #[account(seeds = [b"multisig_signer", multisig.key().as_ref()], bump = multisig.signer_bump)]
+    pub multisig_signer: AccountInfo<'info>,
+    #[account(
+        mut,
+        has_one = multisig,
+        seeds = [b"multisig_tx", multisig.key().as_ref(), &transaction.index.to_le_bytes()],
+        bump = transaction.bump
+    )]
+    pub transaction: Account<'info, MultisigTransaction>,
+}
+
+pub fn execute_multisig_transaction_handler(ctx: Context<ExecuteMultisigTransaction>) -> Result<()> {
+    let multisig = &ctx.accounts.multisig;
+    let tx = &mut ctx.accounts.transaction;
+    if tx.executed { return err!(CustomErrorCode::AlreadyExecuted); }
+    if tx.owner_set_seqno != multisig.owner_set_seqno { return err!(CustomErrorCode::MultisigChanged); }
+    if tx.approval_count() < multisig.threshold as usize { return err!(CustomErrorCode::NotEnoughApprovals); }
+    tx.executed = true;
+
+    let signer_key = ctx.accounts.multisig_signer.key();
+    let ix = Instruction {
+        program_id: crate::ID,
+        accounts: tx.accounts.iter().map(|a| AccountMeta {
+            pubkey: a.pubkey,
+            is_signer: a.is_signer || a.pubkey == signer_key,
+            is_writable: a.is_writable,
+        }).collect(),
+        data: tx.data.clone(),
+    };
+    let multisig_key = multisig.key();
+    let seeds = &[b"multisig_signer".as_ref(), multisig_key.as_ref(), &[multisig.signer_bump]];
+    invoke_signed(&ix, ctx.remaining_accounts, &[&seeds[..]])?;
+    emit!(MultisigTransactionExecuted { multisig: multisig_key, transaction: tx.key(), index: tx.index });
+    Ok(())
 }
 
 // --- Mock Oracle ---
+// One mock feed per `seed`, only in `mock-oracle` builds.
+#[cfg(feature = "mock-oracle")]
 #[derive(Accounts)]
+#[instruction(seed: Pubkey)]
 pub struct SetMockPrice<'info> {
-    #[account(init_if_needed, payer = admin, space = MockPriceAccount::LEN, seeds = [b"mock_oracle"], bump)]
+    #[account(init_if_needed, payer = admin, space = MockPriceAccount::LEN, seeds = [b"mock_oracle", seed.as_ref()], bump)]
     pub mock_price_account: Account<'info, MockPriceAccount>,
+    #[account(seeds = [b"global_state"], bump = global_state.bump, has_one = admin @ CustomErrorCode::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
     #[account(mut)] pub admin: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
-pub fn set_mock_price_handler(ctx: Context<SetMockPrice>, price: u64) -> Result<()> {
-    ctx.accounts.mock_price_account.price = price; Ok(())
+#[cfg(feature = "mock-oracle")]
+pub fn set_mock_price_handler(ctx: Context<SetMockPrice>, seed: Pubkey, price: u64) -> Result<()> {
+    if price == 0 { return err!(CustomErrorCode::InvalidOracleParams); }
+    let feed = &mut ctx.accounts.mock_price_account;
+    let old_price = feed.price;
+    feed.price = price; feed.seed = seed; feed.bump = ctx.bumps.mock_price_account;
+    emit!(MockPriceUpdated { seed, old_price, new_price: price }); Ok(())
 }
 
 #[program]
@@ -520,6 +2347,7 @@ pub mod stablecoin_system {
         ctx: Context<ConfigureCollateral>,
         collateral_mint: Pubkey,
         oracle: Pubkey,
+        oracle_source: OracleSource,
         mcr: u64,
         ltr: u64,
         liquidation_penalty: u64,
@@ -528,18 +2356,45 @@ pub mod stablecoin_system {
             ctx,
             collateral_mint,
             oracle,
+            oracle_source,
             mcr,
             ltr,
             liquidation_penalty,
         )
     }
 
+    pub fn set_timelock_delay(ctx: Context<SetTimelockDelay>, delay: i64) -> Result<()> {
+        set_timelock_delay_handler(ctx, delay)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn queue_change(
+        ctx: Context<QueueChange>,
+        oracle: Pubkey,
+        oracle_source: OracleSource,
+        mcr: u64,
+        ltr: u64,
+        liquidation_penalty: u64,
+        extra_oracles: Vec<OracleFeed>,
+        max_deviation_bps: u64,
+    ) -> Result<()> {
+        queue_change_handler(ctx, oracle, oracle_source, mcr, ltr, liquidation_penalty, extra_oracles, max_deviation_bps)
+    }
+
+    pub fn execute_change(ctx: Context<ResolveChange>) -> Result<()> {
+        execute_change_handler(ctx)
+    }
+
+    pub fn cancel_change(ctx: Context<ResolveChange>) -> Result<()> {
+        cancel_change_handler(ctx)
+    }
+
     pub fn deposit_collateral(ctx: Context<DepositCollateral>, amount: u64) -> Result<()> {
         deposit_collateral_handler(ctx, amount)
     }
 
-    pub fn mint_usdt(ctx: Context<MintUsdt>, amount: u64) -> Result<()> {
-        mint_usdt_handler(ctx, amount)
+    pub fn mint_usdt(ctx: Context<MintUsdt>, amount: u64, max_price: Option<u64>) -> Result<()> {
+        mint_usdt_handler(ctx, amount, max_price)
     }
 
     pub fn burn_usdt(ctx: Context<BurnUsdt>, amount: u64) -> Result<()> {
@@ -550,8 +2405,88 @@ pub mod stablecoin_system {
         withdraw_collateral_handler(ctx, amount)
     }
 
-    pub fn liquidate(ctx: Context<Liquidate>, amount: u64) -> Result<()> {
-        liquidate_handler(ctx, amount)
+    pub fn kick(ctx: Context<Kick>) -> Result<()> {
+        kick_handler(ctx)
+    }
+
+    pub fn take(ctx: Context<Take>, amount: u64, max_price: u64) -> Result<()> {
+        take_handler(ctx, amount, max_price)
+    }
+
+    pub fn redo(ctx: Context<Redo>) -> Result<()> {
+        redo_handler(ctx)
+    }
+
+    pub fn set_auction_params(ctx: Context<SetAuctionParams>, params: AuctionParams) -> Result<()> {
+        set_auction_params_handler(ctx, params)
+    }
+
+    pub fn apply_collateral_params(ctx: Context<ApplyCollateralParams>) -> Result<()> {
+        apply_collateral_params_handler(ctx)
+    }
+
+    pub fn recognize_bad_debt(ctx: Context<RecognizeBadDebt>) -> Result<()> {
+        recognize_bad_debt_handler(ctx)
+    }
+
+    pub fn cover_bad_debt(ctx: Context<CoverBadDebt>) -> Result<()> {
+        cover_bad_debt_handler(ctx)
+    }
+
+    pub fn set_backstop_limit(ctx: Context<SetBackstopLimit>, limit: u64) -> Result<()> {
+        set_backstop_limit_handler(ctx, limit)
+    }
+
+    pub fn set_liquidation_params(ctx: Context<SetLiquidationParams>, close_factor_bps: u64, dust: u64, target_cr: u64) -> Result<()> {
+        set_liquidation_params_handler(ctx, close_factor_bps, dust, target_cr)
+    }
+
+    pub fn configure_treasury(ctx: Context<ConfigureTreasury>, params: TreasuryParams) -> Result<()> {
+        configure_treasury_handler(ctx, params)
+    }
+
+    pub fn apply_treasury_params(ctx: Context<ApplyTreasuryParams>) -> Result<()> {
+        apply_treasury_params_handler(ctx)
+    }
+
+    pub fn withdraw_surplus(ctx: Context<WithdrawSurplus>, amount: u64) -> Result<()> {
+        withdraw_surplus_handler(ctx, amount)
+    }
+
+    pub fn set_stability_fee(ctx: Context<SetStabilityFee>, fee_bps: u64) -> Result<()> {
+        set_stability_fee_handler(ctx, fee_bps)
+    }
+
+    pub fn collect_stability_fees(ctx: Context<CollectStabilityFees>) -> Result<()> {
+        collect_stability_fees_handler(ctx)
+    }
+
+    pub fn set_debt_ceiling(ctx: Context<SetDebtCeiling>, ceiling: u64) -> Result<()> {
+        set_debt_ceiling_handler(ctx, ceiling)
+    }
+
+    pub fn set_global_debt_ceiling(ctx: Context<SetGlobalDebtCeiling>, ceiling: u64) -> Result<()> {
+        set_global_debt_ceiling_handler(ctx, ceiling)
+    }
+
+    pub fn set_oracle_params(ctx: Context<SetOracleParams>, max_staleness_secs: u64, max_confidence_bps: u64) -> Result<()> {
+        set_oracle_params_handler(ctx, max_staleness_secs, max_confidence_bps)
+    }
+
+    pub fn poke(ctx: Context<Poke>) -> Result<()> {
+        poke_handler(ctx)
+    }
+
+    pub fn set_price_cache_delay(ctx: Context<SetPriceCacheDelay>, delay_secs: u64) -> Result<()> {
+        set_price_cache_delay_handler(ctx, delay_secs)
+    }
+
+    pub fn freeze_price_cache(ctx: Context<FreezePriceCache>, frozen: bool) -> Result<()> {
+        freeze_price_cache_handler(ctx, frozen)
+    }
+
+    pub fn post_governance_price(ctx: Context<PostGovernancePrice>, feed_id: [u8; 32], price: u64) -> Result<()> {
+        post_governance_price_handler(ctx, feed_id, price)
     }
 
     pub fn toggle_pause(ctx: Context<TogglePause>, paused: bool) -> Result<()> {
@@ -562,19 +2497,131 @@ pub mod stablecoin_system {
         toggle_freeze_handler(ctx, frozen)
     }
 
-    pub fn configure_psm(ctx: Context<ConfigurePsm>, fee_bps: u64) -> Result<()> {
-        configure_psm_handler(ctx, fee_bps)
+    pub fn propose_admin(ctx: Context<ProposeAdmin>, new_admin: Pubkey) -> Result<()> {
+        propose_admin_handler(ctx, new_admin)
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        accept_admin_handler(ctx)
+    }
+
+    pub fn cancel_admin_transfer(ctx: Context<ProposeAdmin>) -> Result<()> {
+        cancel_admin_transfer_handler(ctx)
+    }
+
+    pub fn grant_role(ctx: Context<ManageRole>, role: Role, member: Pubkey) -> Result<()> {
+        grant_role_handler(ctx, role, member)
+    }
+
+    pub fn revoke_role(ctx: Context<ManageRole>, role: Role) -> Result<()> {
+        revoke_role_handler(ctx, role)
+    }
+
+    pub fn configure_psm(ctx: Context<ConfigurePsm>, oracle_source: OracleSource, tin_bps: u64, tout_bps: u64) -> Result<()> {
+        configure_psm_handler(ctx, oracle_source, tin_bps, tout_bps)
+    }
+
+    pub fn set_psm_fees(ctx: Context<SetPsmFees>, tin_bps: u64, tout_bps: u64) -> Result<()> {
+        set_psm_fees_handler(ctx, tin_bps, tout_bps)
+    }
+
+    pub fn set_psm_limits(
+        ctx: Context<SetPsmLimits>,
+        mint_cap: u64,
+        window_secs: i64,
+        window_limit: u64,
+        min_price: u64,
+        max_price: u64,
+    ) -> Result<()> {
+        set_psm_limits_handler(ctx, mint_cap, window_secs, window_limit, min_price, max_price)
+    }
+
+    pub fn set_psm_oracle_params(ctx: Context<SetPsmLimits>, max_staleness_secs: u64, max_confidence_bps: u64) -> Result<()> {
+        set_psm_oracle_params_handler(ctx, max_staleness_secs, max_confidence_bps)
+    }
+
+    pub fn apply_psm_params(ctx: Context<ApplyPsmParams>) -> Result<()> {
+        apply_psm_params_handler(ctx)
+    }
+
+    pub fn collect_psm_fees(ctx: Context<CollectPsmFees>) -> Result<()> {
+        collect_psm_fees_handler(ctx)
+    }
+
+    pub fn swap_usdc_to_usdt(ctx: Context<SwapUsdcToUsdt>, amount: u64, min_amount_out: u64) -> Result<()> {
+        swap_to_usdt_handler(ctx, amount, min_amount_out)
+    }
+
+    pub fn swap_usdt_to_usdc(ctx: Context<SwapUsdtToUsdc>, amount: u64, min_amount_out: u64) -> Result<()> {
+        swap_to_usdc_handler(ctx, amount, min_amount_out)
+    }
+
+    pub fn create_multisig(ctx: Context<CreateMultisig>, signers: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        create_multisig_handler(ctx, signers, threshold)
+    }
+
+    pub fn update_multisig(ctx: Context<UpdateMultisig>, signers: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        update_multisig_handler(ctx, signers, threshold)
+    }
+
+    pub fn propose_multisig_transaction(
+        ctx: Context<ProposeMultisigTransaction>,
+        accounts: Vec<TxAccountMeta>,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        propose_multisig_transaction_handler(ctx, accounts, data)
+    }
+
+    pub fn approve_multisig_transaction(ctx: Context<ApproveMultisigTransaction>) -> Result<()> {
+        approve_multisig_transaction_handler(ctx)
+    }
+
+    pub fn execute_multisig_transaction(ctx: Context<ExecuteMultisigTransaction>) -> Result<()> {
+        execute_multisig_transaction_handler(ctx)
+    }
+
+    pub fn check_solvency(ctx: Context<CheckSolvency>) -> Result<()> {
+        check_solvency_handler(ctx)
+    }
+
+    pub fn global_settle(ctx: Context<GlobalSettle>, wait_secs: i64) -> Result<()> {
+        global_settle_handler(ctx, wait_secs)
+    }
+
+    pub fn settle_collateral(ctx: Context<SettleCollateral>) -> Result<()> {
+        settle_collateral_handler(ctx)
+    }
+
+    pub fn settle_auction(ctx: Context<SettleAuction>) -> Result<()> {
+        settle_auction_handler(ctx)
+    }
+
+    pub fn settle_position(ctx: Context<SettlePosition>) -> Result<()> {
+        settle_position_handler(ctx)
+    }
+
+    pub fn fix_settlement(ctx: Context<FixSettlement>) -> Result<()> {
+        fix_settlement_handler(ctx)
+    }
+
+    pub fn open_collateral_pool(ctx: Context<OpenCollateralPool>) -> Result<()> {
+        open_collateral_pool_handler(ctx)
+    }
+
+    pub fn open_psm_pool(ctx: Context<OpenPsmPool>) -> Result<()> {
+        open_psm_pool_handler(ctx)
     }
 
-    pub fn swap_usdc_to_usdt(ctx: Context<SwapUsdcToUsdt>, amount: u64) -> Result<()> {
-        swap_to_usdt_handler(ctx, amount)
+    pub fn redeem(ctx: Context<Redeem>, amount: u64) -> Result<()> {
+        redeem_handler(ctx, amount)
     }
 
-    pub fn swap_usdt_to_usdc(ctx: Context<SwapUsdtToUsdc>, amount: u64) -> Result<()> {
-        swap_to_usdc_handler(ctx, amount)
+    pub fn cash(ctx: Context<Cash>) -> Result<()> {
+        cash_handler(ctx)
     }
 
-    pub fn set_mock_price(ctx: Context<SetMockPrice>, price: u64) -> Result<()> {
-        set_mock_price_handler(ctx, price)
+    #[cfg(feature = "mock-oracle")]
+    pub fn set_mock_price(ctx: Context<SetMockPrice>, seed: Pubkey, price: u64) -> Result<()> {
+        set_mock_price_handler(ctx, seed, price)
     }
 }
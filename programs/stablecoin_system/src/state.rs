@@ -1,16 +1,195 @@
 use anchor_lang::prelude::*;
 
+use crate::utils::target_cr_repay;
+
+pub const BPS_DENOMINATOR: u64 = 10_000;
+pub const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+pub const RATE_PRECISION: u128 = 1_000_000_000_000_000_000; // 1e18 = rate index of 1.0
+pub const DEFAULT_MAX_STALENESS_SECS: u64 = 60;
+pub const DEFAULT_MAX_CONFIDENCE_BPS: u64 = 200; // 2% of price
+pub const DEFAULT_MAX_DEVIATION_BPS: u64 = 500;  // 5% from the median
+pub const MAX_EXTRA_ORACLES: usize = 4;
+pub const DEFAULT_PRICE_DELAY_SECS: u64 = 3_600;
+pub const MAX_PRICE_DELAY_SECS: u64 = 86_400;
+/// Floor for the price cache delay, so a spot tick is never served the moment it's read.
+/// Mainnet builds keep five minutes; other builds allow a second for local tests.
+#[cfg(feature = "mainnet")]
+pub const MIN_PRICE_DELAY_SECS: u64 = 5 * 60;
+#[cfg(not(feature = "mainnet"))]
+pub const MIN_PRICE_DELAY_SECS: u64 = 1;
+pub const MAX_SETTLEMENT_WAIT_SECS: i64 = 30 * 86_400;
+/// Floor for the risk-parameter timelock, which is also where it starts. Mainnet builds keep
+/// a full day; other builds allow a second so local tests can run queued changes.
+#[cfg(feature = "mainnet")]
+pub const MIN_TIMELOCK_DELAY: i64 = 24 * 60 * 60;
+#[cfg(not(feature = "mainnet"))]
+pub const MIN_TIMELOCK_DELAY: i64 = 1;
+
 #[account]
 pub struct GlobalState {
     pub admin: Pubkey,
+    pub pending_admin: Pubkey, // Proposed admin awaiting acceptance (default = none)
     pub usdt_mint: Pubkey,
-    pub total_supply: u64,
+    pub total_supply: u64, // cdp_supply + psm_supply; must equal the USDT mint supply
     pub paused: bool,
     pub bump: u8,
+    pub timelock_delay: i64, // Seconds a queued risk-parameter change must wait
+    pub debt_ceiling: u64,   // Max total CDP debt across all collateral types
+    pub total_debt: u64,     // Sum of CollateralConfig.total_debt
+    pub cdp_supply: u64,     // USDT minted against positions (including collected fees), net of burns
+    pub psm_supply: u64,     // USDT minted by PSM swaps, net of redemptions
+    pub settled: bool,       // Set once by global_settle; the system stays paused for good
+    pub pending_timelock_delay: i64, // Shorter delay waiting out the current one
+    pub timelock_delay_eta: i64,     // When `pending_timelock_delay` takes over (0 = none)
+    pub psm_count: u32,              // PSMs configured, each with a fee vault settlement must see
+}
+
+/// Which side of the protocol a USDT mint or burn belongs to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SupplySource {
+    Cdp,
+    Psm,
 }
 
 impl GlobalState {
-    pub const LEN: usize = 8 + 32 + 32 + 8 + 1 + 1;
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 8 + 1 + 1 + 8 + 8 + 8 + 8 + 8 + 1 + 8 + 8 + 4;
+
+    /// Timelock delay in force at `now`, counting a pending reduction once it is due.
+    pub fn timelock_delay_at(&self, now: i64) -> i64 {
+        if self.timelock_delay_eta != 0 && now >= self.timelock_delay_eta {
+            self.pending_timelock_delay
+        } else {
+            self.timelock_delay
+        }
+    }
+
+    fn supply_mut(&mut self, source: SupplySource) -> &mut u64 {
+        match source {
+            SupplySource::Cdp => &mut self.cdp_supply,
+            SupplySource::Psm => &mut self.psm_supply,
+        }
+    }
+
+    pub fn record_mint(&mut self, source: SupplySource, amount: u64) -> Result<()> {
+        let supply = self.supply_mut(source);
+        *supply = supply.checked_add(amount).ok_or(CustomErrorCode::MathOverflow)?;
+        self.total_supply = self.total_supply.checked_add(amount).ok_or(CustomErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn record_burn(&mut self, source: SupplySource, amount: u64) -> Result<()> {
+        let supply = self.supply_mut(source);
+        *supply = supply.checked_sub(amount).ok_or(CustomErrorCode::SolvencyCheckFailed)?;
+        self.total_supply = self.total_supply.checked_sub(amount).ok_or(CustomErrorCode::SolvencyCheckFailed)?;
+        Ok(())
+    }
+
+    /// Books USDT paid against CDP debt and returns how much of it may be burned. Debt rounds
+    /// up in the protocol's favour while fees accrue rounded down, so repaying all of it can
+    /// exceed what CDP supply ever minted; callers send that rounding to surplus instead of
+    /// burning it, and PSM supply is never touched.
+    pub fn record_debt_burn(&mut self, amount: u64) -> Result<u64> {
+        let burnable = amount.min(self.cdp_supply);
+        self.record_burn(SupplySource::Cdp, burnable)?;
+        Ok(burnable)
+    }
+
+    /// Settlement redemptions burn USDT of either origin: CDP supply first, then PSM.
+    pub fn record_redemption_burn(&mut self, amount: u64) -> Result<()> {
+        let from_cdp = amount.min(self.cdp_supply);
+        self.record_burn(SupplySource::Cdp, from_cdp)?;
+        self.record_burn(SupplySource::Psm, amount - from_cdp)
+    }
+
+    /// Tracked supply must be internally consistent and match the USDT mint.
+    pub fn check_solvency(&self, mint_supply: u64) -> Result<()> {
+        let tracked = self.cdp_supply.checked_add(self.psm_supply).ok_or(CustomErrorCode::MathOverflow)?;
+        if tracked != self.total_supply || self.total_supply != mint_supply {
+            return err!(CustomErrorCode::SolvencyCheckFailed);
+        }
+        Ok(())
+    }
+}
+
+/// Operational roles that can be delegated away from the cold admin key.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Role {
+    Pauser,      // toggle_pause
+    Compliance,  // toggle_freeze
+    RiskManager, // configure_collateral, configure_psm
+    OracleAdmin, // post_governance_price
+}
+
+/// On-chain format of a price account.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum OracleKind {
+    #[default]
+    PythLegacy,  // pyth-sdk-solana push price account
+    PythPull,    // Pyth receiver PriceUpdateV2 account
+    Switchboard, // Switchboard V2 aggregator account
+    Governance,  // GovernancePrice account posted by the oracle admin
+}
+
+/// How to read an oracle account: its format and, for pull updates, the Pyth feed ID it must carry.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct OracleSource {
+    pub kind: OracleKind,
+    pub feed_id: [u8; 32], // Pyth pull feed ID or GovernancePrice ID; ignored otherwise
+}
+
+impl OracleSource {
+    pub const LEN: usize = 1 + 32;
+}
+
+/// An oracle account and how to read it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct OracleFeed {
+    pub oracle: Pubkey, // Default = unused slot
+    pub source: OracleSource,
+}
+
+impl OracleFeed {
+    pub const LEN: usize = 32 + OracleSource::LEN;
+}
+
+#[account]
+pub struct Roles {
+    pub pauser: Pubkey,
+    pub compliance: Pubkey,
+    pub risk_manager: Pubkey,
+    pub oracle_admin: Pubkey,
+    pub bump: u8,
+}
+
+impl Roles {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 32 + 1;
+
+    pub fn holder(&self, role: Role) -> Pubkey {
+        match role {
+            Role::Pauser => self.pauser,
+            Role::Compliance => self.compliance,
+            Role::RiskManager => self.risk_manager,
+            Role::OracleAdmin => self.oracle_admin,
+        }
+    }
+
+    pub fn set_holder(&mut self, role: Role, member: Pubkey) {
+        match role {
+            Role::Pauser => self.pauser = member,
+            Role::Compliance => self.compliance = member,
+            Role::RiskManager => self.risk_manager = member,
+            Role::OracleAdmin => self.oracle_admin = member,
+        }
+    }
+
+    /// The admin implicitly holds every role; otherwise the signer must be the assigned holder.
+    pub fn is_authorized(&self, role: Role, global_state: &GlobalState, signer: &Pubkey) -> bool {
+        if *signer == global_state.admin {
+            return true;
+        }
+        let holder = self.holder(role);
+        holder != Pubkey::default() && holder == *signer
+    }
 }
 
 #[account]
@@ -21,10 +200,661 @@ pub struct CollateralConfig {
     pub ltr: u64,                 // Liquidation Threshold Ratio (e.g., 120%)
     pub liquidation_penalty: u64, // Penalty applied during liquidation
     pub bump: u8,
+    pub stability_fee_bps: u64,      // Annualized stability fee (e.g., 200 = 2%)
+    pub cumulative_rate: u128,       // Debt per unit of normalized debt, scaled by RATE_PRECISION
+    pub last_accrual: i64,           // Timestamp of the last cumulative_rate update
+    pub total_normalized_debt: u128, // Sum of Position.normalized_debt for this collateral
+    pub unclaimed_fees: u64,         // Accrued fees not yet minted to the surplus account
+    pub debt_ceiling: u64,           // Max total debt against this collateral
+    pub total_debt: u64,             // Outstanding debt (total_normalized_debt * cumulative_rate)
+    pub max_staleness_secs: u64,     // Oldest oracle publish time accepted
+    pub max_confidence_bps: u64,     // Widest oracle confidence interval accepted, relative to price
+    pub oracle_source: OracleSource, // Format of `oracle`
+    pub extra_oracles: [OracleFeed; MAX_EXTRA_ORACLES], // Medianized with `oracle` when set
+    pub max_deviation_bps: u64,      // Max distance of any source from the median before failing closed
+    pub auction: AuctionParams,      // How liquidation auctions of this collateral run
+    pub close_factor_bps: u64,       // Max share of a position's debt kicked at once
+    pub dust: u64,                   // Min debt left after a partial kick; smaller remainders are kicked too
+    pub target_cr: u64,              // Kick only enough to restore this CR (percent); 0 = off
+    pub settlement_price: u64,       // Frozen at global settlement; 0 = live
+    pub settled_collateral: u64,     // Collateral taken from settled positions for USDT redemptions
+    pub pending_params: CollateralParams, // Looser params waiting out the timelock
+    pub pending_params_eta: i64,          // When the pending params can be applied (0 = none)
 }
 
 impl CollateralConfig {
-    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 8 + 1;
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 8 + 1 + 8 + 16 + 8 + 16 + 8 + 8 + 8 + 8 + 8 + OracleSource::LEN + OracleFeed::LEN * MAX_EXTRA_ORACLES + 8 + AuctionParams::LEN + 8 + 8 + 8 + 8 + 8 + CollateralParams::LEN + 8;
+    pub const MAX_STABILITY_FEE_BPS: u64 = 5_000;
+    pub const MAX_MCR: u64 = 1_000;              // 1000%
+    pub const MAX_LIQUIDATION_PENALTY: u64 = 50; // 50%
+
+    pub fn is_initialized(&self) -> bool {
+        self.collateral_mint != Pubkey::default()
+    }
+
+    /// MCR gates minting and withdrawing; LTR (at or below MCR) gates liquidation.
+    pub fn validate_risk_params(mcr: u64, ltr: u64, liquidation_penalty: u64) -> Result<()> {
+        if mcr <= 100 || mcr > Self::MAX_MCR {
+            return err!(CustomErrorCode::InvalidMcr);
+        }
+        if ltr <= 100 || ltr > mcr {
+            return err!(CustomErrorCode::InvalidLtr);
+        }
+        if liquidation_penalty > Self::MAX_LIQUIDATION_PENALTY {
+            return err!(CustomErrorCode::InvalidLiquidationPenalty);
+        }
+        Ok(())
+    }
+
+    fn covers(collateral_value: u128, debt: u64, ratio: u64) -> Result<bool> {
+        let required = (debt as u128).checked_mul(ratio as u128).ok_or(CustomErrorCode::MathOverflow)? / 100;
+        Ok(collateral_value >= required)
+    }
+
+    /// Fails unless `collateral_value` covers `debt` at MCR. Reports `BelowLtr` when the
+    /// result would be immediately liquidatable.
+    pub fn check_mcr(&self, collateral_value: u128, debt: u64) -> Result<()> {
+        if !Self::covers(collateral_value, debt, self.ltr)? {
+            return err!(CustomErrorCode::BelowLtr);
+        }
+        if !Self::covers(collateral_value, debt, self.mcr)? {
+            return err!(CustomErrorCode::BelowMcr);
+        }
+        Ok(())
+    }
+
+    pub fn is_liquidatable(&self, collateral_value: u128, debt: u64) -> Result<bool> {
+        Ok(!Self::covers(collateral_value, debt, self.ltr)?)
+    }
+
+    /// Close factor in (0, 100%]; target CR off, or above both LTR and 100% + penalty so
+    /// that kicking debt can raise the CR at all.
+    pub fn validate_liquidation_params(&self, close_factor_bps: u64, target_cr: u64) -> Result<()> {
+        if close_factor_bps == 0 || close_factor_bps > BPS_DENOMINATOR {
+            return err!(CustomErrorCode::InvalidLiquidationParams);
+        }
+        if target_cr != 0 && (target_cr < self.ltr || target_cr <= 100 + self.liquidation_penalty || target_cr > Self::MAX_MCR) {
+            return err!(CustomErrorCode::InvalidLiquidationParams);
+        }
+        Ok(())
+    }
+
+    /// Debt to kick from a position with `debt` against `collateral_value`: at most the
+    /// close factor, no more than restores `target_cr`, and all of it if the remainder
+    /// would be dust.
+    pub fn kick_amount(&self, collateral_value: u128, debt: u64) -> Result<u64> {
+        let capped = (debt as u128) * (self.close_factor_bps as u128) / (BPS_DENOMINATOR as u128);
+        let mut amount = capped as u64;
+        if self.target_cr != 0 {
+            if let Some(repay) = target_cr_repay(collateral_value, debt, self.target_cr, self.liquidation_penalty) {
+                amount = amount.min(repay);
+            }
+        }
+        if amount == 0 || debt - amount < self.dust {
+            amount = debt;
+        }
+        Ok(amount)
+    }
+
+    /// What an auction must raise to repay `debt`: the debt plus the liquidation penalty.
+    pub fn liquidation_tab(&self, debt: u64) -> Result<u64> {
+        let tab = (debt as u128).checked_mul(100 + self.liquidation_penalty as u128).ok_or(CustomErrorCode::MathOverflow)? / 100;
+        Ok(u64::try_from(tab).map_err(|_| CustomErrorCode::MathOverflow)?)
+    }
+
+    pub fn set_risk_params(&mut self, oracle: Pubkey, oracle_source: OracleSource, mcr: u64, ltr: u64, liquidation_penalty: u64) {
+        self.oracle = oracle;
+        self.oracle_source = oracle_source;
+        self.mcr = mcr;
+        self.ltr = ltr;
+        self.liquidation_penalty = liquidation_penalty;
+    }
+
+    pub fn params(&self) -> CollateralParams {
+        CollateralParams {
+            max_staleness_secs: self.max_staleness_secs,
+            max_confidence_bps: self.max_confidence_bps,
+            debt_ceiling: self.debt_ceiling,
+            close_factor_bps: self.close_factor_bps,
+            dust: self.dust,
+            target_cr: self.target_cr,
+            auction: self.auction,
+        }
+    }
+
+    /// Installs `params`, dropping any queued change, and returns the previous set.
+    pub fn set_params(&mut self, params: CollateralParams) -> CollateralParams {
+        let old = self.params();
+        self.max_staleness_secs = params.max_staleness_secs;
+        self.max_confidence_bps = params.max_confidence_bps;
+        self.debt_ceiling = params.debt_ceiling;
+        self.close_factor_bps = params.close_factor_bps;
+        self.dust = params.dust;
+        self.target_cr = params.target_cr;
+        self.auction = params.auction;
+        self.pending_params_eta = 0;
+        old
+    }
+
+    /// Grows `cumulative_rate` by the stability fee for the time elapsed since the last
+    /// accrual and books the resulting fees against the outstanding normalized debt.
+    pub fn accrue(&mut self, now: i64) -> Result<()> {
+        if now <= self.last_accrual {
+            return Ok(());
+        }
+        let elapsed = (now - self.last_accrual) as u128;
+        let rate_delta = self.cumulative_rate
+            .checked_mul((self.stability_fee_bps as u128).checked_mul(elapsed).ok_or(CustomErrorCode::MathOverflow)?)
+            .ok_or(CustomErrorCode::MathOverflow)?
+            .checked_div(BPS_DENOMINATOR as u128 * SECONDS_PER_YEAR as u128)
+            .ok_or(CustomErrorCode::MathOverflow)?;
+        let fees = self.total_normalized_debt
+            .checked_mul(rate_delta)
+            .ok_or(CustomErrorCode::MathOverflow)?
+            / RATE_PRECISION;
+        self.unclaimed_fees = self.unclaimed_fees.checked_add(u64::try_from(fees).map_err(|_| CustomErrorCode::MathOverflow)?).ok_or(CustomErrorCode::MathOverflow)?;
+        self.cumulative_rate = self.cumulative_rate.checked_add(rate_delta).ok_or(CustomErrorCode::MathOverflow)?;
+        self.last_accrual = now;
+        Ok(())
+    }
+
+    /// Actual debt for a normalized amount, rounded up in the protocol's favour.
+    pub fn debt_of(&self, normalized_debt: u64) -> Result<u64> {
+        self.scale_debt(normalized_debt as u128)
+    }
+
+    fn scale_debt(&self, normalized_debt: u128) -> Result<u64> {
+        let debt = normalized_debt
+            .checked_mul(self.cumulative_rate)
+            .ok_or(CustomErrorCode::MathOverflow)?
+            .div_ceil(RATE_PRECISION);
+        u64::try_from(debt).map_err(|_| error!(CustomErrorCode::MathOverflow))
+    }
+
+    /// Recomputes `total_debt` after accrual or debt changes and carries the delta into
+    /// the global total.
+    pub fn sync_total_debt(&mut self, global_state: &mut GlobalState) -> Result<()> {
+        let new_total = self.scale_debt(self.total_normalized_debt)?;
+        global_state.total_debt = global_state.total_debt
+            .saturating_sub(self.total_debt)
+            .checked_add(new_total)
+            .ok_or(CustomErrorCode::MathOverflow)?;
+        self.total_debt = new_total;
+        Ok(())
+    }
+
+    /// Fails if either this collateral's or the global debt ceiling is exceeded.
+    pub fn check_debt_ceilings(&self, global_state: &GlobalState) -> Result<()> {
+        if self.total_debt > self.debt_ceiling {
+            return err!(CustomErrorCode::DebtCeilingExceeded);
+        }
+        if global_state.total_debt > global_state.debt_ceiling {
+            return err!(CustomErrorCode::GlobalDebtCeilingExceeded);
+        }
+        Ok(())
+    }
+
+    /// Adds `amount` of actual debt to `position`, rounding the normalized amount up.
+    pub fn add_debt(&mut self, position: &mut Position, amount: u64) -> Result<()> {
+        let normalized = (amount as u128)
+            .checked_mul(RATE_PRECISION)
+            .ok_or(CustomErrorCode::MathOverflow)?
+            .div_ceil(self.cumulative_rate);
+        let normalized = u64::try_from(normalized).map_err(|_| CustomErrorCode::MathOverflow)?;
+        position.normalized_debt = position.normalized_debt.checked_add(normalized).ok_or(CustomErrorCode::MathOverflow)?;
+        self.total_normalized_debt = self.total_normalized_debt.checked_add(normalized as u128).ok_or(CustomErrorCode::MathOverflow)?;
+        position.debt_amount = self.debt_of(position.normalized_debt)?;
+        Ok(())
+    }
+
+    /// Removes up to `amount` of actual debt from `position` and returns the amount removed.
+    /// Repaying the full debt clears the normalized balance exactly.
+    pub fn remove_debt(&mut self, position: &mut Position, amount: u64) -> Result<u64> {
+        let debt = self.debt_of(position.normalized_debt)?;
+        let repaid = amount.min(debt);
+        let normalized = if repaid == debt {
+            position.normalized_debt
+        } else {
+            let n = (repaid as u128).checked_mul(RATE_PRECISION).ok_or(CustomErrorCode::MathOverflow)? / self.cumulative_rate;
+            (n as u64).min(position.normalized_debt)
+        };
+        position.normalized_debt -= normalized;
+        self.total_normalized_debt = self.total_normalized_debt.saturating_sub(normalized as u128);
+        position.debt_amount = self.debt_of(position.normalized_debt)?;
+        Ok(repaid)
+    }
+}
+
+/// Dutch-auction settings for one collateral. The price starts at the cached price times
+/// `buffer_bps` and falls linearly to zero over `duration_secs`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AuctionParams {
+    pub buffer_bps: u64,    // Starting price relative to the cached price (>= 100%)
+    pub duration_secs: u64, // Time for the price to decay to zero
+    pub tail_secs: u64,     // Age after which an auction must be redone
+    pub cusp_bps: u64,      // Drop (relative to the starting price) below which it must be redone
+    pub tip: u64,           // Flat USDT paid from surplus to whoever kicks or redoes
+    pub chip_bps: u64,      // Plus this share of the auction's tab
+    pub chost: u64,         // Minimum tab for `tip` and `chip` to be paid
+}
+
+impl Default for AuctionParams {
+    fn default() -> Self {
+        Self { buffer_bps: 12_000, duration_secs: 3_600, tail_secs: 1_800, cusp_bps: 4_000, tip: 0, chip_bps: 0, chost: 0 }
+    }
+}
+
+impl AuctionParams {
+    pub const LEN: usize = 8 * 7;
+    pub const MAX_BUFFER_BPS: u64 = 50_000; // 5x the cached price
+    pub const MAX_CHIP_BPS: u64 = 100;      // 1% of the tab
+
+    pub fn validate(&self) -> Result<()> {
+        if self.buffer_bps < BPS_DENOMINATOR
+            || self.buffer_bps > Self::MAX_BUFFER_BPS
+            || self.duration_secs == 0
+            || self.tail_secs > self.duration_secs
+            || self.cusp_bps >= BPS_DENOMINATOR
+            // Without a cusp, the tail alone must end the auction before its price reaches zero.
+            || (self.cusp_bps == 0 && self.tail_secs >= self.duration_secs)
+            || self.chip_bps > Self::MAX_CHIP_BPS
+        {
+            return err!(CustomErrorCode::InvalidAuctionParams);
+        }
+        Ok(())
+    }
+
+    /// Whether moving from `current` to these params sells collateral cheaper or pays keepers
+    /// more out of surplus.
+    pub fn loosens(&self, current: &AuctionParams) -> bool {
+        self.buffer_bps < current.buffer_bps
+            || self.duration_secs < current.duration_secs
+            || self.tail_secs > current.tail_secs
+            || self.cusp_bps < current.cusp_bps
+            || self.tip > current.tip
+            || self.chip_bps > current.chip_bps
+            || self.chost < current.chost
+    }
+
+    /// Keeper reward for starting or restarting an auction raising `tab`. Tabs below
+    /// `chost` earn nothing, so dust auctions can't be farmed for the flat tip.
+    pub fn incentive(&self, tab: u64) -> Result<u64> {
+        if tab < self.chost {
+            return Ok(0);
+        }
+        let chip = (tab as u128) * (self.chip_bps as u128) / (BPS_DENOMINATOR as u128);
+        u64::try_from(chip).ok().and_then(|c| c.checked_add(self.tip)).ok_or(error!(CustomErrorCode::MathOverflow))
+    }
+}
+
+/// Per-collateral settings that change without going through `queue_change`. Oracle checks,
+/// the debt ceiling and liquidation terms apply at once when tightened and wait out the
+/// timelock when loosened.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CollateralParams {
+    pub max_staleness_secs: u64,
+    pub max_confidence_bps: u64,
+    pub debt_ceiling: u64,
+    pub close_factor_bps: u64,
+    pub dust: u64,
+    pub target_cr: u64,
+    pub auction: AuctionParams,
+}
+
+impl CollateralParams {
+    pub const LEN: usize = 8 * 6 + AuctionParams::LEN;
+
+    /// Whether moving from `current` to these params accepts worse prices, allows more debt,
+    /// or takes more from borrowers at liquidation. A target CR that is switched off or
+    /// raised kicks more of each position.
+    pub fn loosens(&self, current: &CollateralParams) -> bool {
+        self.max_staleness_secs > current.max_staleness_secs
+            || self.max_confidence_bps > current.max_confidence_bps
+            || self.debt_ceiling > current.debt_ceiling
+            || self.close_factor_bps > current.close_factor_bps
+            || self.dust > current.dust
+            || (current.target_cr != 0 && (self.target_cr == 0 || self.target_cr > current.target_cr))
+            || self.auction.loosens(&current.auction)
+    }
+}
+
+/// A running Dutch auction of collateral kicked from one position.
+#[account]
+pub struct Auction {
+    pub position: Pubkey,
+    pub owner: Pubkey, // Receives any collateral left once `tab` is raised
+    pub collateral_mint: Pubkey,
+    pub keeper: Pubkey, // Paid the account rent back when the auction closes
+    pub lot: u64,       // Collateral left to sell
+    pub tab: u64,       // USDT left to raise, penalty included
+    pub debt: u64,      // Part of `tab` that repays debt and is burned; the rest is penalty
+    pub top: u64,       // Starting price (6 decimals, USD per whole token)
+    pub start_time: i64,
+    pub bump: u8,
+}
+
+impl Auction {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 1;
+
+    /// Restarts the price curve from `price` (the cached price) at `now`.
+    pub fn reset(&mut self, price: u64, params: &AuctionParams, now: i64) -> Result<()> {
+        let top = (price as u128) * (params.buffer_bps as u128) / (BPS_DENOMINATOR as u128);
+        self.top = u64::try_from(top).map_err(|_| CustomErrorCode::MathOverflow)?;
+        self.start_time = now;
+        Ok(())
+    }
+
+    /// An auction past its tail, below its cusp or decayed to zero can no longer be taken,
+    /// only redone.
+    pub fn needs_redo(&self, price: u64, params: &AuctionParams, now: i64) -> bool {
+        let elapsed = now.saturating_sub(self.start_time);
+        price == 0
+            || elapsed > params.tail_secs as i64
+            || (price as u128) * (BPS_DENOMINATOR as u128) < (self.top as u128) * (params.cusp_bps as u128)
+    }
+}
+
+/// Protocol-wide bad debt: USDT still in circulation after the collateral behind it ran out.
+/// It is covered by burning USDT from surplus first and then from the governance backstop.
+#[account]
+pub struct SystemDebt {
+    pub bad_debt: u64,            // Recognized and not yet covered
+    pub total_recognized: u64,
+    pub covered_by_surplus: u64,
+    pub covered_by_backstop: u64,
+    pub backstop_limit: u64,      // What the backstop may still cover; set by the admin
+    pub bump: u8,
+}
+
+impl SystemDebt {
+    pub const LEN: usize = 8 + 8 + 8 + 8 + 8 + 8 + 1;
+
+    pub fn recognize(&mut self, amount: u64) -> Result<()> {
+        self.bad_debt = self.bad_debt.checked_add(amount).ok_or(CustomErrorCode::MathOverflow)?;
+        self.total_recognized = self.total_recognized.checked_add(amount).ok_or(CustomErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Books as much bad debt as `surplus` and then `backstop` (balances available to burn)
+    /// can cover, and returns how much to burn from each.
+    pub fn cover(&mut self, surplus: u64, backstop: u64) -> (u64, u64) {
+        let from_surplus = self.bad_debt.min(surplus);
+        let from_backstop = (self.bad_debt - from_surplus).min(backstop).min(self.backstop_limit);
+        self.bad_debt -= from_surplus + from_backstop;
+        self.backstop_limit -= from_backstop;
+        self.covered_by_surplus += from_surplus;
+        self.covered_by_backstop += from_backstop;
+        (from_surplus, from_backstop)
+    }
+}
+
+/// How protocol revenue is split and how fast governance may withdraw surplus.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TreasuryParams {
+    pub surplus_bps: u64,       // Share kept in the surplus buffer
+    pub liquidator_bps: u64,    // Share of liquidation penalties left with the taker
+    pub fee_recipient_bps: u64, // Share paid to the treasury's fee recipient
+    pub buffer_floor: u64,      // Surplus withdrawals must leave, on top of outstanding bad debt
+    pub window_secs: i64,       // Length of the withdrawal rate-limit window
+    pub window_limit: u64,      // Max USDT withdrawn per window
+}
+
+impl Default for TreasuryParams {
+    fn default() -> Self {
+        Self { surplus_bps: BPS_DENOMINATOR, liquidator_bps: 0, fee_recipient_bps: 0, buffer_floor: 0, window_secs: 86_400, window_limit: 0 }
+    }
+}
+
+impl TreasuryParams {
+    pub const LEN: usize = 8 * 6;
+
+    pub fn validate(&self) -> Result<()> {
+        let total = self.surplus_bps as u128 + self.liquidator_bps as u128 + self.fee_recipient_bps as u128;
+        if total != BPS_DENOMINATOR as u128 || self.window_secs <= 0 {
+            return err!(CustomErrorCode::InvalidTreasuryParams);
+        }
+        Ok(())
+    }
+
+    /// Whether moving from `current` to these params lets more surplus or revenue out.
+    pub fn loosens(&self, current: &TreasuryParams) -> bool {
+        self.window_limit > current.window_limit
+            || self.buffer_floor < current.buffer_floor
+            || self.window_secs < current.window_secs
+            || self.fee_recipient_bps > current.fee_recipient_bps
+    }
+}
+
+/// Where protocol revenue lands. USDT surplus itself sits in the `surplus` token account;
+/// this account holds the split, the fee recipient and the withdrawal limits.
+#[account]
+pub struct Treasury {
+    pub params: TreasuryParams,
+    pub fee_recipient: Pubkey, // USDT token account; default = none
+    pub window_start: i64,
+    pub window_withdrawn: u64,
+    pub total_penalties: u64,  // Liquidation penalties collected, all shares included
+    pub total_psm_fees: u64,   // PSM fees collected, all shares included
+    pub bump: u8,
+    pub pending_params: TreasuryParams, // Looser params waiting out the timelock
+    pub pending_fee_recipient: Pubkey,
+    pub pending_eta: i64, // When the pending params can be applied (0 = none)
+}
+
+impl Treasury {
+    pub const LEN: usize = 8 + TreasuryParams::LEN + 32 + 8 + 8 + 8 + 8 + 1 + TreasuryParams::LEN + 32 + 8;
+
+    /// Installs `params` and `fee_recipient`, dropping any queued change, and returns the
+    /// previous pair.
+    pub fn set_params(&mut self, params: TreasuryParams, fee_recipient: Pubkey) -> (TreasuryParams, Pubkey) {
+        let old = (self.params, self.fee_recipient);
+        self.params = params;
+        self.fee_recipient = fee_recipient;
+        self.pending_eta = 0;
+        old
+    }
+
+    /// Splits `amount` of revenue into (surplus, liquidator, fee recipient) shares. Without a
+    /// liquidator (PSM fees), its share stays in surplus.
+    pub fn split(&self, amount: u64, to_liquidator: bool) -> (u64, u64, u64) {
+        let share = |bps: u64| ((amount as u128) * (bps as u128) / (BPS_DENOMINATOR as u128)) as u64;
+        let liquidator = if to_liquidator { share(self.params.liquidator_bps) } else { 0 };
+        let fee_recipient = share(self.params.fee_recipient_bps);
+        (amount - liquidator - fee_recipient, liquidator, fee_recipient)
+    }
+
+    /// Books a withdrawal of `amount` from a surplus of `surplus`, which must leave the buffer
+    /// floor plus `bad_debt` behind and stay within the current window's limit.
+    pub fn record_withdrawal(&mut self, amount: u64, surplus: u64, bad_debt: u64, now: i64) -> Result<()> {
+        if now.saturating_sub(self.window_start) >= self.params.window_secs {
+            self.window_start = now;
+            self.window_withdrawn = 0;
+        }
+        let reserved = bad_debt.saturating_add(self.params.buffer_floor);
+        let window_withdrawn = self.window_withdrawn.checked_add(amount).ok_or(CustomErrorCode::MathOverflow)?;
+        if amount > surplus.saturating_sub(reserved) || window_withdrawn > self.params.window_limit {
+            return err!(CustomErrorCode::TreasuryWithdrawalExceeded);
+        }
+        self.window_withdrawn = window_withdrawn;
+        Ok(())
+    }
+}
+
+/// Kind of protocol revenue routed through the treasury.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RevenueSource {
+    LiquidationPenalty,
+    PsmFee,
+}
+
+/// Global settlement, created once by `global_settle`. After `wait_secs`, the USDT supply left
+/// in circulation is fixed and each holder can redeem a pro-rata share of every pool.
+#[account]
+pub struct Settlement {
+    pub settled_at: i64,
+    pub wait_secs: i64,          // Time for keepers to settle positions before the supply is fixed
+    pub redeemable_supply: u64,  // USDT outside surplus when fixed; 0 until then
+    pub fixed: bool,
+    pub bump: u8,
+}
+
+impl Settlement {
+    pub const LEN: usize = 8 + 8 + 8 + 8 + 1 + 1;
+
+    pub fn fix(&mut self, redeemable_supply: u64, now: i64) -> Result<()> {
+        if self.fixed {
+            return err!(CustomErrorCode::SettlementAlreadyFixed);
+        }
+        if now < self.settled_at.saturating_add(self.wait_secs) {
+            return err!(CustomErrorCode::SettlementWaitNotElapsed);
+        }
+        self.redeemable_supply = redeemable_supply;
+        self.fixed = true;
+        Ok(())
+    }
+
+    /// Share of a pool of `pool` tokens owed for `burned` redeemed USDT.
+    pub fn share(&self, pool: u64, burned: u64) -> u64 {
+        if self.redeemable_supply == 0 {
+            return 0;
+        }
+        ((pool as u128) * (burned as u128) / (self.redeemable_supply as u128)) as u64
+    }
+}
+
+/// Tokens one vault (a collateral `vault` or a `psm_vault`) holds for USDT redemptions.
+#[account]
+pub struct SettlementPool {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub amount: u64, // Pool size; redemptions are shares of it. Collateral pools grow as positions settle
+    pub psm: bool,   // Whether `vault` is a PSM vault (signed by psm_authority)
+    pub bump: u8,
+}
+
+impl SettlementPool {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 1 + 1;
+}
+
+/// USDT a holder has burned for redemption.
+#[account]
+pub struct Redemption {
+    pub holder: Pubkey,
+    pub burned: u64,
+    pub bump: u8,
+}
+
+impl Redemption {
+    pub const LEN: usize = 8 + 32 + 8 + 1;
+}
+
+/// What a holder has already cashed out of one settlement pool.
+#[account]
+pub struct RedemptionClaim {
+    pub holder: Pubkey,
+    pub pool: Pubkey,
+    pub cashed: u64,
+    pub bump: u8,
+}
+
+impl RedemptionClaim {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 1;
+}
+
+/// A queued update to a collateral's risk parameters, executable once `eta` has passed.
+#[account]
+pub struct PendingChange {
+    pub collateral_mint: Pubkey,
+    pub oracle: Pubkey,
+    pub mcr: u64,
+    pub ltr: u64,
+    pub liquidation_penalty: u64,
+    pub eta: i64, // Earliest unix timestamp at which the change can be executed
+    pub bump: u8,
+    pub oracle_source: OracleSource,
+    pub extra_oracles: [OracleFeed; MAX_EXTRA_ORACLES],
+    pub max_deviation_bps: u64,
+}
+
+impl PendingChange {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 1 + OracleSource::LEN + OracleFeed::LEN * MAX_EXTRA_ORACLES + 8;
+}
+
+/// Oracle Security Module price cache for one collateral. Liquidations use `current_price`,
+/// which lags the oracle by one `delay_secs` hop, so a bad tick sits in `next_price` long
+/// enough for governance to freeze the cache before it takes effect. Liquidations stop while
+/// the cache is frozen or has gone unpoked for too long.
+#[account]
+pub struct PriceCache {
+    pub collateral_mint: Pubkey,
+    pub current_price: u64, // 6 decimals (USD)
+    pub next_price: u64,    // Becomes current on the next poke
+    pub last_poke: i64,
+    pub delay_secs: u64,    // Minimum time between pokes
+    pub frozen: bool,
+    pub bump: u8,
+    pub pending_delay_secs: u64, // Shorter delay waiting out the timelock
+    pub delay_eta: i64,          // When `pending_delay_secs` takes over (0 = none)
+}
+
+impl PriceCache {
+    pub const LEN: usize = 8 + 32 + 8 + 8 + 8 + 8 + 1 + 1 + 8 + 8;
+    pub const MIN_MAX_AGE_SECS: u64 = 60;
+
+    /// Starts the cache at `price`, so liquidations have a price from the moment of listing.
+    pub fn seed(&mut self, collateral_mint: Pubkey, price: u64, now: i64) {
+        self.collateral_mint = collateral_mint;
+        self.current_price = price;
+        self.next_price = price;
+        self.last_poke = now;
+        self.delay_secs = DEFAULT_PRICE_DELAY_SECS;
+    }
+
+    /// Delay in force at `now`, counting a pending reduction once it is due.
+    pub fn delay_at(&self, now: i64) -> u64 {
+        if self.delay_eta != 0 && now >= self.delay_eta {
+            self.pending_delay_secs
+        } else {
+            self.delay_secs
+        }
+    }
+
+    /// Rolls `next_price` into `current_price` and queues `price`, at most once per delay.
+    pub fn poke(&mut self, price: u64, now: i64) -> Result<()> {
+        if self.frozen { return err!(CustomErrorCode::PriceCacheFrozen); }
+        if now < self.last_poke.saturating_add(self.delay_at(now) as i64) {
+            return err!(CustomErrorCode::PriceCacheNotReady);
+        }
+        self.current_price = self.next_price;
+        self.next_price = price;
+        self.last_poke = now;
+        Ok(())
+    }
+
+    /// Oldest poke whose price liquidations accept: two delays, so a single missed poke is
+    /// tolerated, but never less than a minute.
+    pub fn max_age_secs(&self, now: i64) -> u64 {
+        self.delay_at(now).saturating_mul(2).max(Self::MIN_MAX_AGE_SECS)
+    }
+
+    /// `current_price` as of `now`, refused while frozen or once it has outlived
+    /// `max_age_secs`.
+    pub fn liquidation_price(&self, now: i64) -> Result<u64> {
+        if self.frozen { return err!(CustomErrorCode::PriceCacheFrozen); }
+        if now.saturating_sub(self.last_poke) > self.max_age_secs(now) as i64 {
+            return err!(CustomErrorCode::PriceCacheStale);
+        }
+        if self.current_price == 0 { return err!(CustomErrorCode::PriceCacheNotReady); }
+        Ok(self.current_price)
+    }
+
+    /// Stops pokes and drops the queued price, so unfreezing can't roll a bad tick in.
+    pub fn set_frozen(&mut self, frozen: bool) {
+        self.frozen = frozen;
+        if frozen {
+            self.next_price = self.current_price;
+        }
+    }
 }
 
 #[account]
@@ -32,23 +862,254 @@ pub struct Position {
     pub owner: Pubkey,
     pub collateral_mint: Pubkey,
     pub collateral_amount: u64,
-    pub debt_amount: u64,
+    pub debt_amount: u64, // Actual debt as of the last touch (normalized_debt * cumulative_rate)
     pub is_frozen: bool,
     pub last_updated: i64,
     pub bump: u8,
+    pub normalized_debt: u64, // Debt divided by the collateral's cumulative_rate at borrow time
 }
 
 impl Position {
-    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 1 + 8 + 1;
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 1 + 8 + 1 + 8;
+}
+
+#[account]
+pub struct PsmConfig {
+    pub token_mint: Pubkey,
+    pub vault: Pubkey,
+    pub total_minted: u64, // USDT liability: PSM-minted USDT not yet redeemed
+    pub tin_bps: u64,  // Fee on backing -> USDT swaps, taken in USDT
+    pub tout_bps: u64, // Fee on USDT -> backing swaps, taken in USDT
+    pub bump: u8,
+    pub oracle: Pubkey,
+    pub fee_vault: Pubkey, // USDT account collecting tin/tout fees
+    pub mint_cap: u64,     // Hard cap on total_minted
+    pub window_secs: i64,  // Length of the rate-limit window
+    pub window_limit: u64, // Max USDT minted (and, separately, redeemed) per window
+    pub window_updated_at: i64, // Last time the windowed totals were decayed
+    pub window_minted: u64,
+    pub window_redeemed: u64,
+    pub min_price: u64, // Backing price band (6 decimals) accepted for minting
+    pub max_price: u64,
+    pub reserves: u64, // Backing tokens (in the backing mint's units) held against total_minted
+    pub max_staleness_secs: u64,
+    pub max_confidence_bps: u64,
+    pub oracle_source: OracleSource,
+    pub pending_params: PsmParams, // Looser limits waiting out the timelock
+    pub pending_params_eta: i64,   // When the pending params can be applied (0 = none)
+}
+
+/// PSM limits and oracle checks. Tightening applies at once; loosening waits out the timelock.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct PsmParams {
+    pub mint_cap: u64,
+    pub window_secs: i64,
+    pub window_limit: u64,
+    pub min_price: u64,
+    pub max_price: u64,
+    pub max_staleness_secs: u64,
+    pub max_confidence_bps: u64,
+}
+
+impl PsmParams {
+    pub const LEN: usize = 8 * 7;
+
+    /// Whether moving from `current` to these params lets more USDT be minted or redeemed,
+    /// sooner, or against worse prices.
+    pub fn loosens(&self, current: &PsmParams) -> bool {
+        self.mint_cap > current.mint_cap
+            || self.window_secs < current.window_secs
+            || self.window_limit > current.window_limit
+            || self.min_price < current.min_price
+            || self.max_price > current.max_price
+            || self.max_staleness_secs > current.max_staleness_secs
+            || self.max_confidence_bps > current.max_confidence_bps
+    }
+}
+
+impl PsmConfig {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 8 + 1 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + OracleSource::LEN + PsmParams::LEN + 8;
+    pub const MAX_FEE_BPS: u64 = 1_000; // 10%
+    pub const DEFAULT_WINDOW_SECS: i64 = 86_400;
+
+    pub fn validate_limits(window_secs: i64, min_price: u64, max_price: u64) -> Result<()> {
+        if window_secs <= 0 || min_price > max_price {
+            return err!(CustomErrorCode::InvalidPsmLimits);
+        }
+        Ok(())
+    }
+
+    pub fn params(&self) -> PsmParams {
+        PsmParams {
+            mint_cap: self.mint_cap,
+            window_secs: self.window_secs,
+            window_limit: self.window_limit,
+            min_price: self.min_price,
+            max_price: self.max_price,
+            max_staleness_secs: self.max_staleness_secs,
+            max_confidence_bps: self.max_confidence_bps,
+        }
+    }
+
+    /// Installs `params`, dropping any queued change, and returns the previous set.
+    pub fn set_params(&mut self, params: PsmParams) -> PsmParams {
+        let old = self.params();
+        self.mint_cap = params.mint_cap;
+        self.window_secs = params.window_secs;
+        self.window_limit = params.window_limit;
+        self.min_price = params.min_price;
+        self.max_price = params.max_price;
+        self.max_staleness_secs = params.max_staleness_secs;
+        self.max_confidence_bps = params.max_confidence_bps;
+        self.pending_params_eta = 0;
+        old
+    }
+
+    /// Decays the windowed totals linearly over `window_secs`. A sliding window keeps any
+    /// `window_secs` span within the limit, where resetting at fixed boundaries would let
+    /// twice the limit through around each reset.
+    fn roll_window(&mut self, now: i64) {
+        let elapsed = now.saturating_sub(self.window_updated_at).clamp(0, self.window_secs);
+        let (left, window) = ((self.window_secs - elapsed) as u128, self.window_secs as u128);
+        self.window_minted = ((self.window_minted as u128) * left / window) as u64;
+        self.window_redeemed = ((self.window_redeemed as u128) * left / window) as u64;
+        self.window_updated_at = now;
+    }
+
+    /// Refuses to mint against backing priced outside `[min_price, max_price]`.
+    pub fn check_price_band(&self, price: u64) -> Result<()> {
+        if price < self.min_price || price > self.max_price {
+            return err!(CustomErrorCode::PsmPriceOutOfBand);
+        }
+        Ok(())
+    }
+
+    /// Books `amount` USDT minted against `backing` tokens deposited, enforcing the hard
+    /// cap and the window limit.
+    pub fn record_mint(&mut self, amount: u64, backing: u64, now: i64) -> Result<()> {
+        self.roll_window(now);
+        let total = self.total_minted.checked_add(amount).ok_or(CustomErrorCode::MathOverflow)?;
+        if total > self.mint_cap {
+            return err!(CustomErrorCode::PsmMintCapExceeded);
+        }
+        let windowed = self.window_minted.checked_add(amount).ok_or(CustomErrorCode::MathOverflow)?;
+        if windowed > self.window_limit {
+            return err!(CustomErrorCode::PsmRateLimitExceeded);
+        }
+        self.reserves = self.reserves.checked_add(backing).ok_or(CustomErrorCode::MathOverflow)?;
+        self.total_minted = total;
+        self.window_minted = windowed;
+        Ok(())
+    }
+
+    /// Books `amount` USDT burned for `backing` tokens released, enforcing the window limit.
+    pub fn record_redeem(&mut self, amount: u64, backing: u64, now: i64) -> Result<()> {
+        self.roll_window(now);
+        let windowed = self.window_redeemed.checked_add(amount).ok_or(CustomErrorCode::MathOverflow)?;
+        if windowed > self.window_limit {
+            return err!(CustomErrorCode::PsmRateLimitExceeded);
+        }
+        self.total_minted = self.total_minted.checked_sub(amount).ok_or(CustomErrorCode::PsmInsufficientReserves)?;
+        self.reserves = self.reserves.checked_sub(backing).ok_or(CustomErrorCode::PsmInsufficientReserves)?;
+        self.window_redeemed = windowed;
+        Ok(())
+    }
+
+    /// Post-swap invariant: the vault holds at least the booked reserves, and this PSM's
+    /// liability fits within the global PSM supply.
+    pub fn check_invariant(&self, vault_balance: u64, psm_supply: u64) -> Result<()> {
+        if vault_balance < self.reserves || self.total_minted > psm_supply {
+            return err!(CustomErrorCode::PsmInvariantViolated);
+        }
+        Ok(())
+    }
+}
+
+/// M-of-N signer set. Its signer PDA (`[b"multisig_signer", multisig]`) can be installed as
+/// `GlobalState.admin` and acts only through approved `MultisigTransaction`s.
+#[account]
+pub struct Multisig {
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8,
+    pub nonce: u64,           // Index of the next proposed transaction
+    pub owner_set_seqno: u32, // Bumped when signers change, invalidating pending transactions
+    pub signer_bump: u8,
+}
+
+impl Multisig {
+    pub const MAX_SIGNERS: usize = 10;
+    pub const LEN: usize = 8 + (4 + 32 * Self::MAX_SIGNERS) + 1 + 8 + 4 + 1;
+
+    pub fn signer_index(&self, key: &Pubkey) -> Option<usize> {
+        self.signers.iter().position(|s| s == key)
+    }
+
+    pub fn validate_config(signers: &[Pubkey], threshold: u8) -> Result<()> {
+        let unique = signers.iter().enumerate().all(|(i, s)| !signers[..i].contains(s));
+        if signers.is_empty() || signers.len() > Self::MAX_SIGNERS || !unique {
+            return err!(CustomErrorCode::InvalidMultisigConfig);
+        }
+        if threshold == 0 || threshold as usize > signers.len() {
+            return err!(CustomErrorCode::InvalidMultisigConfig);
+        }
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct TxAccountMeta {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+/// A proposed instruction against this program, executed with the multisig signer PDA
+/// once `threshold` signers have approved.
+#[account]
+pub struct MultisigTransaction {
+    pub multisig: Pubkey,
+    pub index: u64,
+    pub accounts: Vec<TxAccountMeta>,
+    pub data: Vec<u8>,
+    pub approvals: Vec<bool>,
+    pub executed: bool,
+    pub owner_set_seqno: u32,
+    pub proposer: Pubkey,
+    pub bump: u8,
+}
+
+impl MultisigTransaction {
+    pub fn space(num_accounts: usize, data_len: usize, num_signers: usize) -> usize {
+        8 + 32 + 8 + (4 + num_accounts * (32 + 1 + 1)) + (4 + data_len) + (4 + num_signers) + 1 + 4 + 32 + 1
+    }
+
+    pub fn approval_count(&self) -> usize {
+        self.approvals.iter().filter(|a| **a).count()
+    }
+}
+
+/// Price posted directly by the oracle admin, usable as one source among several.
+#[account]
+pub struct GovernancePrice {
+    pub feed_id: [u8; 32],
+    pub price: u64, // 6 decimals (USD)
+    pub publish_time: i64,
+    pub bump: u8,
+}
+
+impl GovernancePrice {
+    pub const LEN: usize = 8 + 32 + 8 + 8 + 1;
 }
 
 #[account]
 pub struct MockPriceAccount {
     pub price: u64,
+    pub seed: Pubkey, // One feed per seed, e.g. a collateral or PSM token mint
+    pub bump: u8,
 }
 
 impl MockPriceAccount {
-    pub const LEN: usize = 8 + 8;
+    pub const LEN: usize = 8 + 8 + 32 + 1;
 }
 
 #[error_code]
@@ -67,7 +1128,7 @@ pub enum CustomErrorCode {
     SolvencyCheckFailed,
     #[msg("Insufficient collateral to withdraw.")]
     InsufficientCollateral,
-    #[msg("Position is safe (CR >= MCR).")]
+    #[msg("Position is safe (CR >= LTR).")]
     PositionSafe,
     #[msg("Error loading Oracle Price.")]
     OracleError,
@@ -75,4 +1136,194 @@ pub enum CustomErrorCode {
     OracleStale,
     #[msg("Provided Oracle does not match PSM Configuration.")]
     InvalidOracle,
+    #[msg("Signer is not authorized to perform this action.")]
+    Unauthorized,
+    #[msg("Invalid admin address.")]
+    InvalidAdmin,
+    #[msg("No admin transfer is pending.")]
+    NoPendingAdmin,
+    #[msg("Collateral is already configured; queue the change through the timelock.")]
+    TimelockRequired,
+    #[msg("Timelock delay has not elapsed.")]
+    TimelockNotElapsed,
+    #[msg("Invalid timelock delay.")]
+    InvalidTimelockDelay,
+    #[msg("Invalid multisig signer set or threshold.")]
+    InvalidMultisigConfig,
+    #[msg("Signer is not a member of the multisig.")]
+    NotMultisigSigner,
+    #[msg("Multisig transaction has already been executed.")]
+    AlreadyExecuted,
+    #[msg("Multisig transaction does not have enough approvals.")]
+    NotEnoughApprovals,
+    #[msg("Multisig signers changed since the transaction was proposed.")]
+    MultisigChanged,
+    #[msg("Stability fee exceeds the maximum.")]
+    InvalidStabilityFee,
+    #[msg("LTR must be above 100% and must not exceed MCR.")]
+    InvalidLtr,
+    #[msg("MCR must be above 100% and at most 1000%.")]
+    InvalidMcr,
+    #[msg("Liquidation penalty exceeds the maximum.")]
+    InvalidLiquidationPenalty,
+    #[msg("Oracle account is not a valid price feed.")]
+    InvalidOracleFeed,
+    #[msg("Collateral debt ceiling exceeded.")]
+    DebtCeilingExceeded,
+    #[msg("Global debt ceiling exceeded.")]
+    GlobalDebtCeilingExceeded,
+    #[msg("PSM fee exceeds the maximum.")]
+    InvalidPsmFee,
+    #[msg("Invalid PSM limits.")]
+    InvalidPsmLimits,
+    #[msg("PSM mint cap exceeded.")]
+    PsmMintCapExceeded,
+    #[msg("PSM rate limit exceeded for the current window.")]
+    PsmRateLimitExceeded,
+    #[msg("Backing token price is outside the PSM band.")]
+    PsmPriceOutOfBand,
+    #[msg("PSM reserves or liability too small for this redemption.")]
+    PsmInsufficientReserves,
+    #[msg("PSM reserves no longer back its liability.")]
+    PsmInvariantViolated,
+    #[msg("Output is below the caller's minimum amount out.")]
+    SlippageExceeded,
+    #[msg("Oracle price is above the caller's maximum price.")]
+    PriceAboveMax,
+    #[msg("Invalid oracle staleness or confidence parameters.")]
+    InvalidOracleParams,
+    #[msg("Oracle confidence interval is too wide.")]
+    OracleConfidenceTooWide,
+    #[msg("Too many oracle sources.")]
+    TooManyOracles,
+    #[msg("A configured oracle account was not supplied.")]
+    MissingOracleAccount,
+    #[msg("Oracle sources disagree beyond the allowed deviation.")]
+    OracleDeviationExceeded,
+    #[msg("Price cache is frozen.")]
+    PriceCacheFrozen,
+    #[msg("Price cache delay has not elapsed since the last poke.")]
+    PriceCacheNotReady,
+    #[msg("Invalid price cache delay.")]
+    InvalidPriceDelay,
+    #[msg("Invalid auction parameters.")]
+    InvalidAuctionParams,
+    #[msg("Auction is past its tail or cusp and must be redone.")]
+    AuctionNeedsRedo,
+    #[msg("Auction is still running and cannot be redone.")]
+    AuctionNotStale,
+    #[msg("Invalid liquidation parameters.")]
+    InvalidLiquidationParams,
+    #[msg("Position has collateral left or no debt, so it holds no bad debt.")]
+    NoBadDebt,
+    #[msg("Invalid treasury parameters.")]
+    InvalidTreasuryParams,
+    #[msg("Withdrawal exceeds the treasury's limit or dips into reserved surplus.")]
+    TreasuryWithdrawalExceeded,
+    #[msg("The treasury's fee recipient account is required.")]
+    MissingFeeRecipient,
+    #[msg("The system has been globally settled.")]
+    SystemSettled,
+    #[msg("The system has not been globally settled.")]
+    NotSettled,
+    #[msg("Collateral has not been settled yet.")]
+    CollateralNotSettled,
+    #[msg("Collateral has already been settled.")]
+    CollateralAlreadySettled,
+    #[msg("Settlement wait has not elapsed.")]
+    SettlementWaitNotElapsed,
+    #[msg("Redeemable supply has already been fixed.")]
+    SettlementAlreadyFixed,
+    #[msg("Redeemable supply has not been fixed yet.")]
+    SettlementNotFixed,
+    #[msg("Invalid settlement wait.")]
+    InvalidSettlementWait,
+    #[msg("Price cache has not been poked recently enough.")]
+    PriceCacheStale,
+    #[msg("No treasury change is queued.")]
+    NoPendingTreasuryChange,
+    #[msg("Account is not a PSM config, fee vault or collateral config expected for settlement.")]
+    InvalidSettlementAccount,
+    #[msg("A governance price can only be an extra oracle, not the primary source.")]
+    GovernancePrimaryOracle,
+    #[msg("No parameter change is queued.")]
+    NoPendingParamsChange,
+    #[msg("Every PSM config and fee vault must be passed to fix settlement.")]
+    MissingPsmFeeVault,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An account of type `T` with every field zeroed.
+    fn zeroed<T: AccountDeserialize>(len: usize) -> T {
+        T::try_deserialize_unchecked(&mut &vec![0u8; len][..]).unwrap()
+    }
+
+    #[test]
+    fn repays_rounded_up_debt_without_touching_psm_supply() {
+        let mut global_state: GlobalState = zeroed(GlobalState::LEN);
+        global_state.record_mint(SupplySource::Psm, 1_000).unwrap();
+        let mut config: CollateralConfig = zeroed(CollateralConfig::LEN);
+        config.cumulative_rate = RATE_PRECISION;
+        let (mut a, mut b): (Position, Position) = (zeroed(Position::LEN), zeroed(Position::LEN));
+        for position in [&mut a, &mut b] {
+            config.add_debt(position, 100).unwrap();
+            global_state.record_mint(SupplySource::Cdp, 100).unwrap();
+        }
+
+        // 0.5% over a year: 1.0 of fees accrues in aggregate, but each position owes 100.5 rounded up.
+        config.stability_fee_bps = 50;
+        config.accrue(SECONDS_PER_YEAR as i64).unwrap();
+        assert_eq!(config.unclaimed_fees, 1);
+        global_state.record_mint(SupplySource::Cdp, config.unclaimed_fees).unwrap();
+
+        let burned_a = global_state.record_debt_burn(config.remove_debt(&mut a, u64::MAX).unwrap()).unwrap();
+        let repaid_b = config.remove_debt(&mut b, u64::MAX).unwrap();
+        let burned_b = global_state.record_debt_burn(repaid_b).unwrap();
+        assert_eq!((burned_a, repaid_b, burned_b), (101, 101, 100));
+        assert_eq!((global_state.cdp_supply, global_state.psm_supply), (0, 1_000));
+        assert_eq!(config.total_normalized_debt, 0);
+        global_state.check_solvency(1_000).unwrap();
+    }
+
+    #[test]
+    fn flags_collateral_params_that_loosen() {
+        let config: CollateralConfig = zeroed(CollateralConfig::LEN);
+        let current = CollateralParams { debt_ceiling: 1_000, close_factor_bps: 5_000, target_cr: 150, auction: AuctionParams::default(), ..config.params() };
+        assert!(!current.loosens(&current));
+        assert!(!CollateralParams { debt_ceiling: 999, close_factor_bps: 4_000, target_cr: 140, ..current }.loosens(&current));
+        assert!(CollateralParams { debt_ceiling: 1_001, ..current }.loosens(&current));
+        assert!(CollateralParams { close_factor_bps: 5_001, ..current }.loosens(&current));
+        // Switching the target CR off kicks up to the close factor
+        assert!(CollateralParams { target_cr: 0, ..current }.loosens(&current));
+        assert!(!CollateralParams { target_cr: 150, ..current }.loosens(&CollateralParams { target_cr: 0, ..current }));
+        let auction = AuctionParams { duration_secs: 1_800, ..current.auction };
+        assert!(CollateralParams { auction, ..current }.loosens(&current));
+    }
+
+    #[test]
+    fn auctions_must_end_before_their_price_hits_zero() {
+        let params = AuctionParams { cusp_bps: 0, tail_secs: 3_600, duration_secs: 3_600, ..Default::default() };
+        assert!(params.validate().is_err());
+        assert!(AuctionParams { tail_secs: 3_599, ..params }.validate().is_ok());
+        assert!(AuctionParams { cusp_bps: 1, ..params }.validate().is_ok());
+
+        // Even a legacy config that slipped through can't be taken at a zero price.
+        let auction = Auction {
+            position: Pubkey::default(),
+            owner: Pubkey::default(),
+            collateral_mint: Pubkey::default(),
+            keeper: Pubkey::default(),
+            lot: 1,
+            tab: 1,
+            debt: 1,
+            top: 120_000_000,
+            start_time: 0,
+            bump: 0,
+        };
+        assert!(!auction.needs_redo(1, &params, 3_600));
+        assert!(auction.needs_redo(0, &params, 3_600));
+    }
 }
@@ -1,52 +1,701 @@
 use anchor_lang::prelude::*;
 
-use pyth_sdk_solana::load_price_feed_from_account_info;
-use crate::state::CustomErrorCode;
+#[cfg(any(test, not(feature = "mock-oracle")))]
+use pyth_sdk_solana::{state::SolanaPriceAccount, Price, PriceFeed};
+use crate::events::OracleCircuitBreakerTripped;
+use crate::state::{CollateralConfig, CustomErrorCode, GovernancePrice, OracleKind, OracleSource, BPS_DENOMINATOR};
+#[cfg(feature = "mock-oracle")]
 use crate::state::MockPriceAccount;
 
-pub fn get_price(oracle: &AccountInfo) -> Result<u64> {
-    #[cfg(feature = "mock-oracle")]
-    {
-        // Try to read dynamic mock price from account manually to avoid lifetime issues
-        if let Ok(mut data) = oracle.try_borrow_data() {
-            if let Ok(mock_price_acc) = MockPriceAccount::try_deserialize(&mut &data[..]) {
-                msg!("Using Dynamic Mock Oracle Price: ${}", mock_price_acc.price as f64 / 1_000_000.0);
-                return Ok(mock_price_acc.price);
+/// Which edge of the oracle's confidence interval a price is read at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriceBias {
+    Mid,
+    /// `price - conf`: values assets the protocol holds (collateral, PSM backing paid in).
+    Collateral,
+    /// `price + conf`: values what the protocol owes (debt, PSM backing paid out).
+    Debt,
+}
+
+/// USD value (6 decimals) of `amount` collateral base units at `price`.
+pub fn collateral_value(amount: u64, price: u64, decimals: u8) -> Result<u128> {
+    (amount as u128)
+        .checked_mul(price as u128)
+        .and_then(|v| v.checked_div(10u128.pow(decimals as u32)))
+        .ok_or(error!(CustomErrorCode::MathOverflow))
+}
+
+/// Amount of a `decimals`-decimal token worth `value` USD (6 decimals) at `price`.
+pub fn token_amount(value: u64, price: u64, decimals: u8) -> Result<u64> {
+    let amount = (value as u128)
+        .checked_mul(10u128.pow(decimals as u32))
+        .and_then(|v| v.checked_div(price as u128))
+        .ok_or(error!(CustomErrorCode::MathOverflow))?;
+    u64::try_from(amount).map_err(|_| error!(CustomErrorCode::MathOverflow))
+}
+
+/// Fee of `bps` basis points on `amount`, rounded up in the protocol's favour.
+pub fn bps_fee(amount: u64, bps: u64) -> Result<u64> {
+    let fee = (amount as u128)
+        .checked_mul(bps as u128)
+        .ok_or(error!(CustomErrorCode::MathOverflow))?
+        .div_ceil(BPS_DENOMINATOR as u128);
+    u64::try_from(fee).map_err(|_| error!(CustomErrorCode::MathOverflow))
+}
+
+/// Dutch-auction price `elapsed` seconds after starting at `top`, falling linearly to zero
+/// over `duration_secs`.
+pub fn auction_price(top: u64, elapsed: i64, duration_secs: u64) -> u64 {
+    let elapsed = elapsed.max(0) as u64;
+    if elapsed >= duration_secs {
+        return 0;
+    }
+    ((top as u128) * ((duration_secs - elapsed) as u128) / (duration_secs as u128)) as u64
+}
+
+/// Splits a position's `collateral` at global settlement into what covers its `debt` at the
+/// frozen `price` (rounded up, in the protocol's favour) and the excess returned to its owner.
+pub fn settlement_split(collateral: u64, debt: u64, price: u64, decimals: u8) -> Result<(u64, u64)> {
+    if price == 0 {
+        return err!(CustomErrorCode::OracleError);
+    }
+    let owed = (debt as u128)
+        .checked_mul(10u128.pow(decimals as u32))
+        .ok_or(error!(CustomErrorCode::MathOverflow))?
+        .div_ceil(price as u128);
+    let owed = owed.min(collateral as u128) as u64;
+    Ok((owed, collateral - owed))
+}
+
+/// Debt to repay, with a `penalty` percent on top paid out of collateral, to bring a position
+/// with `debt` against `collateral_value` back to `target_cr` percent:
+/// `(value - repay * (100 + penalty) / 100) / (debt - repay) = target_cr / 100`.
+/// Rounded up; `None` when no repayment can reach the target (`target_cr <= 100 + penalty`).
+pub fn target_cr_repay(collateral_value: u128, debt: u64, target_cr: u64, penalty: u64) -> Option<u64> {
+    let (target, cost) = (target_cr as u128, 100 + penalty as u128);
+    if target <= cost {
+        return None;
+    }
+    let shortfall = (debt as u128 * target).saturating_sub(collateral_value.saturating_mul(100));
+    let repay = shortfall.div_ceil(target - cost);
+    Some(repay.min(debt as u128) as u64)
+}
+
+pub fn validate_oracle_params(max_staleness_secs: u64, max_confidence_bps: u64) -> Result<()> {
+    if max_staleness_secs == 0 || max_confidence_bps == 0 || max_confidence_bps > BPS_DENOMINATOR {
+        return err!(CustomErrorCode::InvalidOracleParams);
+    }
+    Ok(())
+}
+
+/// Pyth oracle program, owner of legacy push price accounts. Mainnet and devnet are
+/// served by different deployments.
+#[cfg(all(feature = "mainnet", any(test, not(feature = "mock-oracle"))))]
+pub const PYTH_ORACLE_ID: Pubkey = pubkey!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH");
+#[cfg(all(not(feature = "mainnet"), any(test, not(feature = "mock-oracle"))))]
+pub const PYTH_ORACLE_ID: Pubkey = pubkey!("gSbePebfvPy7tRqimPoVecS2UsBvYv46ynrzWocc92s");
+
+/// Pyth receiver program, owner of `PriceUpdateV2` accounts.
+#[cfg(any(test, not(feature = "mock-oracle")))]
+pub const PYTH_RECEIVER_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+// Anchor discriminator of the receiver's `PriceUpdateV2` account: sha256("account:PriceUpdateV2")[..8].
+#[cfg(any(test, not(feature = "mock-oracle")))]
+const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+
+// Mirrors of the Pyth receiver account layout, so we don't pull in its SDK. Fields we
+// don't read still have to be declared to deserialize the layout.
+#[cfg(any(test, not(feature = "mock-oracle")))]
+#[derive(AnchorDeserialize)]
+#[cfg_attr(test, derive(AnchorSerialize))]
+#[allow(dead_code)]
+enum VerificationLevel {
+    Partial { num_signatures: u8 },
+    Full,
+}
+
+#[cfg(any(test, not(feature = "mock-oracle")))]
+#[derive(AnchorDeserialize)]
+#[cfg_attr(test, derive(AnchorSerialize))]
+#[allow(dead_code)]
+struct PriceFeedMessage {
+    feed_id: [u8; 32],
+    price: i64,
+    conf: u64,
+    exponent: i32,
+    publish_time: i64,
+    prev_publish_time: i64,
+    ema_price: i64,
+    ema_conf: u64,
+}
+
+#[cfg(any(test, not(feature = "mock-oracle")))]
+#[derive(AnchorDeserialize)]
+#[cfg_attr(test, derive(AnchorSerialize))]
+#[allow(dead_code)]
+struct PriceUpdateV2 {
+    write_authority: Pubkey,
+    verification_level: VerificationLevel,
+    price_message: PriceFeedMessage,
+    posted_slot: u64,
+}
+
+/// Switchboard V2 program, owner of aggregator accounts.
+#[cfg(any(test, not(feature = "mock-oracle")))]
+pub const SWITCHBOARD_V2_ID: Pubkey = pubkey!("SW1TCH7qEPTdLsDHRgPuMQjbQxKdH2aBStViMFnt64f");
+
+// Anchor discriminator of Switchboard's `AggregatorAccountData`.
+#[cfg(any(test, not(feature = "mock-oracle")))]
+const AGGREGATOR_DISCRIMINATOR: [u8; 8] = [217, 230, 65, 101, 201, 162, 27, 125];
+
+// Byte offsets into the packed `AggregatorAccountData` (after the discriminator) of the
+// `latest_confirmed_round` fields we read. Decimals are an i128 mantissa and a u32 scale.
+#[cfg(any(test, not(feature = "mock-oracle")))]
+mod aggregator_offsets {
+    pub const NUM_SUCCESS: usize = 8 + 333;
+    pub const ROUND_OPEN_TIMESTAMP: usize = 8 + 350;
+    pub const RESULT: usize = 8 + 358;
+    pub const STD_DEVIATION: usize = 8 + 378;
+    pub const END: usize = STD_DEVIATION + 20;
+}
+
+/// Loads the latest confirmed round of a Switchboard V2 aggregator as a Pyth-style price,
+/// with the round's standard deviation as its confidence.
+#[cfg(any(test, not(feature = "mock-oracle")))]
+fn load_aggregator(oracle: &AccountInfo) -> Result<Price> {
+    use aggregator_offsets::*;
+    if oracle.owner != &SWITCHBOARD_V2_ID {
+        return err!(CustomErrorCode::InvalidOracleFeed);
+    }
+    let data = oracle.try_borrow_data()?;
+    if data.len() < END || data[..8] != AGGREGATOR_DISCRIMINATOR {
+        return err!(CustomErrorCode::InvalidOracleFeed);
+    }
+    let read_u32 = |at: usize| u32::from_le_bytes(data[at..at + 4].try_into().unwrap());
+    let read_decimal = |at: usize| (i128::from_le_bytes(data[at..at + 16].try_into().unwrap()), read_u32(at + 16));
+    if read_u32(NUM_SUCCESS) == 0 {
+        return err!(CustomErrorCode::OracleError); // No confirmed round yet
+    }
+    let publish_time = i64::from_le_bytes(data[ROUND_OPEN_TIMESTAMP..ROUND_OPEN_TIMESTAMP + 8].try_into().unwrap());
+    let (result, scale) = read_decimal(RESULT);
+    let (std_deviation, std_scale) = read_decimal(STD_DEVIATION);
+
+    // Express both at one scale small enough for an i32 exponent and a u64 conf
+    let expo = scale.min(9);
+    let rescale = |mantissa: i128, from: u32| -> Option<i128> {
+        if from >= expo {
+            mantissa.checked_div(10i128.checked_pow(from - expo)?)
+        } else {
+            mantissa.checked_mul(10i128.checked_pow(expo - from)?)
+        }
+    };
+    let price = rescale(result, scale).and_then(|p| i64::try_from(p).ok()).ok_or(error!(CustomErrorCode::OracleError))?;
+    let conf = rescale(std_deviation, std_scale).and_then(|c| u64::try_from(c).ok()).ok_or(error!(CustomErrorCode::OracleError))?;
+    Ok(Price { price, conf, expo: -(expo as i32), publish_time })
+}
+
+/// Loads a legacy Pyth price account owned by the Pyth oracle program.
+#[cfg(any(test, not(feature = "mock-oracle")))]
+fn load_legacy_feed(oracle: &AccountInfo) -> Result<PriceFeed> {
+    if oracle.owner != &PYTH_ORACLE_ID {
+        return err!(CustomErrorCode::InvalidOracleFeed);
+    }
+    SolanaPriceAccount::account_info_to_feed(oracle).map_err(|_| error!(CustomErrorCode::OracleError))
+}
+
+/// Loads a fully verified `PriceUpdateV2` for `feed_id`.
+#[cfg(any(test, not(feature = "mock-oracle")))]
+fn load_price_update(oracle: &AccountInfo, feed_id: &[u8; 32]) -> Result<PriceFeedMessage> {
+    if oracle.owner != &PYTH_RECEIVER_ID {
+        return err!(CustomErrorCode::InvalidOracleFeed);
+    }
+    let data = oracle.try_borrow_data()?;
+    if data.len() < 8 || data[..8] != PRICE_UPDATE_V2_DISCRIMINATOR {
+        return err!(CustomErrorCode::InvalidOracleFeed);
+    }
+    let update = PriceUpdateV2::deserialize(&mut &data[8..]).map_err(|_| error!(CustomErrorCode::InvalidOracleFeed))?;
+    // Partially verified updates were checked against too few Wormhole guardian signatures.
+    if !matches!(update.verification_level, VerificationLevel::Full) || update.price_message.feed_id != *feed_id {
+        return err!(CustomErrorCode::InvalidOracleFeed);
+    }
+    Ok(update.price_message)
+}
+
+/// Checks that `oracle` can serve prices for `source`. Governance prices are read the
+/// same way in every build; other kinds depend on the oracle mode.
+pub fn validate_oracle(oracle: &AccountInfo, source: &OracleSource) -> Result<()> {
+    match source.kind {
+        OracleKind::Governance => load_governance_price(oracle, &source.feed_id).map(|_| ()),
+        _ => validate_feed(oracle, source),
+    }
+}
+
+/// Like `validate_oracle`, for the primary source of a collateral or PSM. Governance prices
+/// are posted by hand and may only back up market feeds as extra sources.
+pub fn validate_primary_oracle(oracle: &AccountInfo, source: &OracleSource) -> Result<()> {
+    if source.kind == OracleKind::Governance {
+        return err!(CustomErrorCode::GovernancePrimaryOracle);
+    }
+    validate_oracle(oracle, source)
+}
+
+/// Loads a program-owned `GovernancePrice` posted for `feed_id`.
+fn load_governance_price(oracle: &AccountInfo, feed_id: &[u8; 32]) -> Result<GovernancePrice> {
+    if oracle.owner != &crate::ID {
+        return err!(CustomErrorCode::InvalidOracleFeed);
+    }
+    let data = oracle.try_borrow_data()?;
+    let posted = GovernancePrice::try_deserialize(&mut &data[..]).map_err(|_| error!(CustomErrorCode::InvalidOracleFeed))?;
+    if posted.feed_id != *feed_id {
+        return err!(CustomErrorCode::InvalidOracleFeed);
+    }
+    Ok(posted)
+}
+
+/// Governance prices carry no confidence interval, so they are used as posted.
+fn read_governance_price(oracle: &AccountInfo, feed_id: &[u8; 32], now: i64, max_staleness_secs: u64) -> Result<u64> {
+    let posted = load_governance_price(oracle, feed_id)?;
+    if now.saturating_sub(posted.publish_time) > max_staleness_secs as i64 {
+        return err!(CustomErrorCode::OracleStale);
+    }
+    if posted.price == 0 {
+        return err!(CustomErrorCode::OracleError);
+    }
+    Ok(posted.price)
+}
+
+/// Mock mode: a program-owned mock price account.
+#[cfg(feature = "mock-oracle")]
+fn validate_feed(oracle: &AccountInfo, _source: &OracleSource) -> Result<()> {
+    load_mock_price(oracle).map(|_| ())
+}
+
+#[cfg(feature = "mock-oracle")]
+fn load_mock_price(oracle: &AccountInfo) -> Result<u64> {
+    if oracle.owner != &crate::ID {
+        return err!(CustomErrorCode::InvalidOracleFeed);
+    }
+    let data = oracle.try_borrow_data()?;
+    let mock = MockPriceAccount::try_deserialize(&mut &data[..]).map_err(|_| error!(CustomErrorCode::InvalidOracleFeed))?;
+    if mock.price == 0 {
+        return err!(CustomErrorCode::OracleError);
+    }
+    Ok(mock.price)
+}
+
+/// A Pyth legacy price account, a verified `PriceUpdateV2` for the configured feed, or a
+/// Switchboard V2 aggregator.
+#[cfg(not(feature = "mock-oracle"))]
+fn validate_feed(oracle: &AccountInfo, source: &OracleSource) -> Result<()> {
+    match source.kind {
+        OracleKind::PythLegacy => load_legacy_feed(oracle)
+            .map(|_| ())
+            .map_err(|_| error!(CustomErrorCode::InvalidOracleFeed)),
+        OracleKind::PythPull => load_price_update(oracle, &source.feed_id).map(|_| ()),
+        OracleKind::Switchboard => load_aggregator(oracle).map(|_| ()),
+        OracleKind::Governance => unreachable!("governance prices are validated by validate_oracle"),
+    }
+}
+
+/// Reads `oracle` as `source`, normalized to 6 decimals (USD).
+pub fn get_price(oracle: &AccountInfo, source: &OracleSource, max_staleness_secs: u64, max_confidence_bps: u64, bias: PriceBias) -> Result<u64> {
+    match source.kind {
+        OracleKind::Governance => {
+            read_governance_price(oracle, &source.feed_id, Clock::get()?.unix_timestamp, max_staleness_secs)
+        }
+        _ => get_feed_price(oracle, source, max_staleness_secs, max_confidence_bps, bias),
+    }
+}
+
+/// Mock prices carry no confidence interval or publish time, so the source, staleness,
+/// confidence and bias arguments are ignored.
+#[cfg(feature = "mock-oracle")]
+fn get_feed_price(oracle: &AccountInfo, _source: &OracleSource, _max_staleness_secs: u64, _max_confidence_bps: u64, _bias: PriceBias) -> Result<u64> {
+    let price = load_mock_price(oracle)?;
+    msg!("Using Mock Oracle Price: ${}", price as f64 / 1_000_000.0);
+    Ok(price)
+}
+
+#[cfg(not(feature = "mock-oracle"))]
+fn get_feed_price(oracle: &AccountInfo, source: &OracleSource, max_staleness_secs: u64, max_confidence_bps: u64, bias: PriceBias) -> Result<u64> {
+    let now = Clock::get()?.unix_timestamp;
+    read_feed_price(oracle, source, now, max_staleness_secs, max_confidence_bps, bias)
+}
+
+/// Reads a Pyth (legacy or pull) or Switchboard price as of `now`, normalized to 6 decimals (USD).
+#[cfg(any(test, not(feature = "mock-oracle")))]
+fn read_feed_price(oracle: &AccountInfo, source: &OracleSource, now: i64, max_staleness_secs: u64, max_confidence_bps: u64, bias: PriceBias) -> Result<u64> {
+    // 1. Load Price Feed and get Valid Price (no older than the configured window)
+    let price_data = match source.kind {
+        OracleKind::PythLegacy => {
+            load_legacy_feed(oracle)?
+                .get_price_no_older_than(now, max_staleness_secs)
+                .ok_or(error!(CustomErrorCode::OracleStale))?
+        }
+        OracleKind::PythPull => {
+            let message = load_price_update(oracle, &source.feed_id)?;
+            if now.saturating_sub(message.publish_time) > max_staleness_secs as i64 {
+                return err!(CustomErrorCode::OracleStale);
             }
+            Price { price: message.price, conf: message.conf, expo: message.exponent, publish_time: message.publish_time }
         }
-        
-        msg!("Using Default Mock Oracle Price: $150.00");
-        return Ok(150_000_000);
-    }
-
-    #[cfg(not(feature = "mock-oracle"))]
-    {
-        // 1. Load Price Feed
-        let price_feed = load_price_feed_from_account_info(oracle)
-            .map_err(|_| error!(CustomErrorCode::OracleError))?;
-
-        // 2. Get Valid Price (no older than 60 seconds)
-        let current_timestamp = Clock::get()?.unix_timestamp;
-        let price_data = price_feed.get_price_no_older_than(current_timestamp, 60)
-            .ok_or(error!(CustomErrorCode::OracleStale))?;
-
-        // 3. Normalize Price to 6 decimals (USD)
-        let target_decimals = 6;
-        let price = price_data.price; // i64
-        let expo = price_data.expo;   // i32
-        
-        if price < 0 {
-            return err!(CustomErrorCode::OracleError); // Negative price?
+        OracleKind::Switchboard => {
+            let price = load_aggregator(oracle)?;
+            if now.saturating_sub(price.publish_time) > max_staleness_secs as i64 {
+                return err!(CustomErrorCode::OracleStale);
+            }
+            price
         }
+        OracleKind::Governance => unreachable!("governance prices are read by get_price"),
+    };
 
-        let exponent = (target_decimals as i32) + expo;
-        
-        let final_price = if exponent >= 0 {
-            (price as u64).checked_mul(10u64.pow(exponent as u32)).ok_or(CustomErrorCode::MathOverflow)?
-        } else {
-            (price as u64).checked_div(10u64.pow(exponent.abs() as u32)).ok_or(CustomErrorCode::MathOverflow)?
+    normalize_price(&price_data, max_confidence_bps, bias)
+}
+
+#[cfg(any(test, not(feature = "mock-oracle")))]
+fn normalize_price(price_data: &Price, max_confidence_bps: u64, bias: PriceBias) -> Result<u64> {
+    if price_data.price <= 0 {
+        return err!(CustomErrorCode::OracleError); // Negative price?
+    }
+    let price = price_data.price as u64;
+    let conf = price_data.conf;
+
+    // 3. Reject prices whose confidence interval is too wide relative to the price
+    if (conf as u128) * (BPS_DENOMINATOR as u128) > (price as u128) * (max_confidence_bps as u128) {
+        return err!(CustomErrorCode::OracleConfidenceTooWide);
+    }
+    let price = match bias {
+        PriceBias::Mid => price,
+        PriceBias::Collateral => price - conf, // conf <= price, guaranteed by the check above
+        PriceBias::Debt => price.checked_add(conf).ok_or(CustomErrorCode::MathOverflow)?,
+    };
+
+    // 4. Normalize Price to 6 decimals (USD)
+    let target_decimals: i32 = 6;
+    let exponent = target_decimals + price_data.expo;
+
+    let final_price = if exponent >= 0 {
+        10u64.checked_pow(exponent as u32).and_then(|s| price.checked_mul(s)).ok_or(CustomErrorCode::MathOverflow)?
+    } else {
+        price.checked_div(10u64.checked_pow(exponent.unsigned_abs()).ok_or(CustomErrorCode::MathOverflow)?).ok_or(CustomErrorCode::MathOverflow)?
+    };
+
+    Ok(final_price)
+}
+
+/// Price of `config`'s collateral: the primary oracle alone, or the median of it and every
+/// configured extra oracle, each looked up by key in `remaining_accounts`. Fails closed if
+/// any source is more than `max_deviation_bps` away from the median.
+pub fn collateral_price(config: &CollateralConfig, oracle: &AccountInfo, remaining_accounts: &[AccountInfo], bias: PriceBias) -> Result<u64> {
+    let read = |info: &AccountInfo, source: &OracleSource| {
+        get_price(info, source, config.max_staleness_secs, config.max_confidence_bps, bias)
+    };
+    let mut prices = vec![read(oracle, &config.oracle_source)?];
+    for feed in config.extra_oracles.iter().filter(|f| f.oracle != Pubkey::default()) {
+        let info = remaining_accounts
+            .iter()
+            .find(|a| a.key == &feed.oracle)
+            .ok_or(error!(CustomErrorCode::MissingOracleAccount))?;
+        prices.push(read(info, &feed.source)?);
+    }
+    if prices.len() == 1 {
+        return Ok(prices[0]);
+    }
+
+    let median = median_price(&prices)?;
+    if exceeds_deviation(&prices, median, config.max_deviation_bps) {
+        // The transaction reverts; the event survives only in its logs (see the event docs).
+        emit!(OracleCircuitBreakerTripped {
+            collateral_mint: config.collateral_mint,
+            prices,
+            median,
+            max_deviation_bps: config.max_deviation_bps,
+        });
+        return err!(CustomErrorCode::OracleDeviationExceeded);
+    }
+    Ok(median)
+}
+
+/// Median of `prices`; the mean of the two middle prices when there is an even number.
+fn median_price(prices: &[u64]) -> Result<u64> {
+    let mut sorted = prices.to_vec();
+    sorted.sort_unstable();
+    let mid = sorted.len() / 2;
+    if sorted.len() % 2 == 1 {
+        return Ok(sorted[mid]);
+    }
+    let sum = (sorted[mid - 1] as u128) + (sorted[mid] as u128);
+    u64::try_from(sum / 2).map_err(|_| error!(CustomErrorCode::MathOverflow))
+}
+
+fn exceeds_deviation(prices: &[u64], median: u64, max_deviation_bps: u64) -> bool {
+    prices.iter().any(|&p| {
+        (p.abs_diff(median) as u128) * (BPS_DENOMINATOR as u128) > (median as u128) * (max_deviation_bps as u128)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyth_sdk_solana::state::{AccountType, PriceInfo, PriceStatus, MAGIC, VERSION_2};
+
+    const NOW: i64 = 1_700_000_000;
+
+    // Raw bytes of a trading Pyth price account publishing `price`/`conf` at `expo`.
+    fn pyth_account(price: i64, conf: u64, expo: i32, publish_time: i64) -> Vec<u8> {
+        let account = SolanaPriceAccount {
+            magic: MAGIC,
+            ver: VERSION_2,
+            atype: AccountType::Price as u32,
+            expo,
+            timestamp: publish_time,
+            agg: PriceInfo { price, conf, status: PriceStatus::Trading, ..Default::default() },
+            ..Default::default()
+        };
+        bytemuck::bytes_of(&account).to_vec()
+    }
+
+    const FEED_ID: [u8; 32] = [7; 32];
+
+    // Raw bytes of a `PriceUpdateV2` carrying `price`/`conf` at `expo` for FEED_ID.
+    fn price_update(price: i64, conf: u64, expo: i32, publish_time: i64, verification_level: VerificationLevel) -> Vec<u8> {
+        let update = PriceUpdateV2 {
+            write_authority: Pubkey::new_unique(),
+            verification_level,
+            price_message: PriceFeedMessage {
+                feed_id: FEED_ID,
+                price,
+                conf,
+                exponent: expo,
+                publish_time,
+                prev_publish_time: publish_time - 1,
+                ema_price: price,
+                ema_conf: conf,
+            },
+            posted_slot: 1,
         };
+        let mut data = PRICE_UPDATE_V2_DISCRIMINATOR.to_vec();
+        update.serialize(&mut data).unwrap();
+        data
+    }
+
+    fn read_as(data: &mut [u8], owner: Pubkey, source: OracleSource, max_staleness_secs: u64, max_confidence_bps: u64, bias: PriceBias) -> Result<u64> {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let info = AccountInfo::new(&key, false, false, &mut lamports, data, &owner, false, 0);
+        read_feed_price(&info, &source, NOW, max_staleness_secs, max_confidence_bps, bias)
+    }
+
+    // Raw bytes of a Switchboard aggregator whose latest round confirmed `result` +/- `std`.
+    fn aggregator(result: (i128, u32), std: (i128, u32), round_open_timestamp: i64, num_success: u32) -> Vec<u8> {
+        use aggregator_offsets::*;
+        let mut data = vec![0u8; END];
+        data[..8].copy_from_slice(&AGGREGATOR_DISCRIMINATOR);
+        data[NUM_SUCCESS..NUM_SUCCESS + 4].copy_from_slice(&num_success.to_le_bytes());
+        data[ROUND_OPEN_TIMESTAMP..ROUND_OPEN_TIMESTAMP + 8].copy_from_slice(&round_open_timestamp.to_le_bytes());
+        for (at, (mantissa, scale)) in [(RESULT, result), (STD_DEVIATION, std)] {
+            data[at..at + 16].copy_from_slice(&mantissa.to_le_bytes());
+            data[at + 16..at + 20].copy_from_slice(&scale.to_le_bytes());
+        }
+        data
+    }
+
+    fn read_switchboard(data: &mut [u8], max_staleness_secs: u64, bias: PriceBias) -> Result<u64> {
+        let source = OracleSource { kind: OracleKind::Switchboard, feed_id: [0; 32] };
+        read_as(data, SWITCHBOARD_V2_ID, source, max_staleness_secs, 200, bias)
+    }
+
+    fn read(data: &mut [u8], max_staleness_secs: u64, max_confidence_bps: u64, bias: PriceBias) -> Result<u64> {
+        read_as(data, PYTH_ORACLE_ID, OracleSource::default(), max_staleness_secs, max_confidence_bps, bias)
+    }
+
+    fn read_pull(data: &mut [u8], max_staleness_secs: u64, bias: PriceBias) -> Result<u64> {
+        let source = OracleSource { kind: OracleKind::PythPull, feed_id: FEED_ID };
+        read_as(data, PYTH_RECEIVER_ID, source, max_staleness_secs, 200, bias)
+    }
+
+    fn error_code(result: Result<u64>) -> u32 {
+        match result.unwrap_err() {
+            Error::AnchorError(e) => e.error_code_number,
+            e => panic!("unexpected error {e:?}"),
+        }
+    }
+
+    #[test]
+    fn normalizes_to_six_decimals() {
+        // $150.00 at expo -8
+        let mut data = pyth_account(15_000_000_000, 0, -8, NOW);
+        assert_eq!(read(&mut data, 60, 100, PriceBias::Mid).unwrap(), 150_000_000);
+        // $1.5 at expo -2 scales up
+        let mut data = pyth_account(150, 0, -2, NOW);
+        assert_eq!(read(&mut data, 60, 100, PriceBias::Mid).unwrap(), 1_500_000);
+    }
+
+    #[test]
+    fn applies_conservative_bias() {
+        // $100 +/- $1 at expo -8
+        let mut data = pyth_account(10_000_000_000, 100_000_000, -8, NOW);
+        assert_eq!(read(&mut data, 60, 200, PriceBias::Mid).unwrap(), 100_000_000);
+        assert_eq!(read(&mut data, 60, 200, PriceBias::Collateral).unwrap(), 99_000_000);
+        assert_eq!(read(&mut data, 60, 200, PriceBias::Debt).unwrap(), 101_000_000);
+    }
+
+    #[test]
+    fn rejects_wide_confidence() {
+        // conf is exactly 1% of price: accepted at 100 bps, rejected at 99 bps
+        let mut data = pyth_account(10_000_000_000, 100_000_000, -8, NOW);
+        assert!(read(&mut data, 60, 100, PriceBias::Mid).is_ok());
+        assert_eq!(
+            error_code(read(&mut data, 60, 99, PriceBias::Mid)),
+            u32::from(CustomErrorCode::OracleConfidenceTooWide)
+        );
+    }
+
+    #[test]
+    fn rejects_stale_price() {
+        let mut data = pyth_account(10_000_000_000, 0, -8, NOW - 61);
+        assert_eq!(
+            error_code(read(&mut data, 60, 100, PriceBias::Mid)),
+            u32::from(CustomErrorCode::OracleStale)
+        );
+        assert!(read(&mut data, 120, 100, PriceBias::Mid).is_ok());
+    }
+
+    #[test]
+    fn rejects_non_positive_and_malformed_feeds() {
+        let mut data = pyth_account(0, 0, -8, NOW);
+        assert_eq!(
+            error_code(read(&mut data, 60, 100, PriceBias::Mid)),
+            u32::from(CustomErrorCode::OracleError)
+        );
+        let mut data = vec![0u8; 64];
+        assert_eq!(
+            error_code(read(&mut data, 60, 100, PriceBias::Mid)),
+            u32::from(CustomErrorCode::OracleError)
+        );
+    }
+
+    #[test]
+    fn rejects_legacy_account_with_wrong_owner() {
+        let mut data = pyth_account(10_000_000_000, 0, -8, NOW);
+        assert!(read(&mut data, 60, 100, PriceBias::Mid).is_ok());
+        // Same bytes under a different owner are not a Pyth price account
+        assert_eq!(
+            error_code(read_as(&mut data, Pubkey::new_unique(), OracleSource::default(), 60, 100, PriceBias::Mid)),
+            u32::from(CustomErrorCode::InvalidOracleFeed)
+        );
+    }
+
+    #[test]
+    fn reads_verified_price_update() {
+        // $100 +/- $1 at expo -8
+        let mut data = price_update(10_000_000_000, 100_000_000, -8, NOW, VerificationLevel::Full);
+        assert_eq!(read_pull(&mut data, 60, PriceBias::Mid).unwrap(), 100_000_000);
+        assert_eq!(read_pull(&mut data, 60, PriceBias::Collateral).unwrap(), 99_000_000);
+    }
+
+    #[test]
+    fn rejects_unverified_or_mismatched_price_update() {
+        let invalid = u32::from(CustomErrorCode::InvalidOracleFeed);
+        let mut data = price_update(10_000_000_000, 0, -8, NOW, VerificationLevel::Partial { num_signatures: 5 });
+        assert_eq!(error_code(read_pull(&mut data, 60, PriceBias::Mid)), invalid);
+
+        let mut data = price_update(10_000_000_000, 0, -8, NOW, VerificationLevel::Full);
+        let other_feed = OracleSource { kind: OracleKind::PythPull, feed_id: [8; 32] };
+        assert_eq!(error_code(read_as(&mut data, PYTH_RECEIVER_ID, other_feed, 60, 200, PriceBias::Mid)), invalid);
+
+        // Same bytes under a different owner are not a receiver account
+        let source = OracleSource { kind: OracleKind::PythPull, feed_id: FEED_ID };
+        assert_eq!(error_code(read_as(&mut data, Pubkey::new_unique(), source, 60, 200, PriceBias::Mid)), invalid);
+
+        // A legacy account is not a price update
+        let mut legacy = pyth_account(10_000_000_000, 0, -8, NOW);
+        assert_eq!(error_code(read_pull(&mut legacy, 60, PriceBias::Mid)), invalid);
+    }
+
+    #[test]
+    fn rejects_stale_price_update() {
+        let mut data = price_update(10_000_000_000, 0, -8, NOW - 61, VerificationLevel::Full);
+        assert_eq!(
+            error_code(read_pull(&mut data, 60, PriceBias::Mid)),
+            u32::from(CustomErrorCode::OracleStale)
+        );
+        assert!(read_pull(&mut data, 61, PriceBias::Mid).is_ok());
+    }
+
+    #[test]
+    fn reads_switchboard_aggregator() {
+        // $100 +/- $1 at scale 12 is rescaled to 9 decimals
+        let mut data = aggregator((100_000_000_000_000, 12), (1, 0), NOW, 3);
+        assert_eq!(read_switchboard(&mut data, 60, PriceBias::Mid).unwrap(), 100_000_000);
+        assert_eq!(read_switchboard(&mut data, 60, PriceBias::Debt).unwrap(), 101_000_000);
+        // $1.5 at scale 1
+        let mut data = aggregator((15, 1), (0, 0), NOW, 1);
+        assert_eq!(read_switchboard(&mut data, 60, PriceBias::Mid).unwrap(), 1_500_000);
+    }
+
+    #[test]
+    fn rejects_invalid_switchboard_aggregator() {
+        let mut stale = aggregator((100, 0), (0, 0), NOW - 61, 1);
+        assert_eq!(error_code(read_switchboard(&mut stale, 60, PriceBias::Mid)), u32::from(CustomErrorCode::OracleStale));
+
+        let mut unconfirmed = aggregator((100, 0), (0, 0), NOW, 0);
+        assert_eq!(error_code(read_switchboard(&mut unconfirmed, 60, PriceBias::Mid)), u32::from(CustomErrorCode::OracleError));
+
+        let mut negative = aggregator((-100, 0), (0, 0), NOW, 1);
+        assert_eq!(error_code(read_switchboard(&mut negative, 60, PriceBias::Mid)), u32::from(CustomErrorCode::OracleError));
+
+        let mut data = aggregator((100, 0), (0, 0), NOW, 1);
+        let source = OracleSource { kind: OracleKind::Switchboard, feed_id: [0; 32] };
+        assert_eq!(
+            error_code(read_as(&mut data, Pubkey::new_unique(), source, 60, 200, PriceBias::Mid)),
+            u32::from(CustomErrorCode::InvalidOracleFeed)
+        );
+    }
+
+    #[test]
+    fn decays_auction_price_linearly() {
+        assert_eq!(auction_price(120_000_000, 0, 3_600), 120_000_000);
+        assert_eq!(auction_price(120_000_000, 900, 3_600), 90_000_000);
+        assert_eq!(auction_price(120_000_000, 3_599, 3_600), 33_333);
+        assert_eq!(auction_price(120_000_000, 3_600, 3_600), 0);
+        // Clock skew before the start reads as the starting price
+        assert_eq!(auction_price(120_000_000, -5, 3_600), 120_000_000);
+    }
+
+    #[test]
+    fn repays_just_enough_to_reach_target_cr() {
+        // $700 backing 600 debt (116%), 10% penalty, target 150%:
+        // (700 - 1.1x) / (600 - x) = 1.5 => x = 500, leaving $150 against 100 debt
+        assert_eq!(target_cr_repay(700_000_000, 600_000_000, 150, 10), Some(500_000_000));
+        // Already at the target: nothing to repay
+        assert_eq!(target_cr_repay(900_000_000, 600_000_000, 150, 10), Some(0));
+        // Deeply underwater: capped at the whole debt
+        assert_eq!(target_cr_repay(100_000_000, 600_000_000, 150, 10), Some(600_000_000));
+        // A target at or below 100% + penalty can't be reached by repaying
+        assert_eq!(target_cr_repay(700_000_000, 600_000_000, 110, 10), None);
+    }
+
+    #[test]
+    fn settles_positions_at_the_frozen_price() {
+        // 10 tokens (9 decimals) @ $70 against 600 debt: 8.571428572 owed, the rest returned
+        assert_eq!(settlement_split(10_000_000_000, 600_000_000, 70_000_000, 9).unwrap(), (8_571_428_572, 1_428_571_428));
+        // Underwater: everything is owed and nothing returned
+        assert_eq!(settlement_split(10_000_000_000, 600_000_000, 10_000_000, 9).unwrap(), (10_000_000_000, 0));
+        // No debt: all collateral goes back
+        assert_eq!(settlement_split(10_000_000_000, 0, 70_000_000, 9).unwrap(), (0, 10_000_000_000));
+        assert!(settlement_split(10_000_000_000, 600_000_000, 0, 9).is_err());
+    }
+
+    #[test]
+    fn medianizes_and_trips_on_deviation() {
+        assert_eq!(median_price(&[101, 99, 100]).unwrap(), 100);
+        assert_eq!(median_price(&[100, 104]).unwrap(), 102);
+        assert_eq!(median_price(&[u64::MAX, u64::MAX]).unwrap(), u64::MAX);
 
-        Ok(final_price)
+        // 5% band around a $100 median: $95 and $105 are the edges
+        let median = median_price(&[95_000_000, 100_000_000, 105_000_000]).unwrap();
+        assert!(!exceeds_deviation(&[95_000_000, 100_000_000, 105_000_000], median, 500));
+        assert!(exceeds_deviation(&[94_999_999, 100_000_000, 105_000_000], median, 500));
     }
 }
@@ -6,6 +6,7 @@ import {
     createAssociatedTokenAccount,
     mintTo,
     getAccount,
+    transfer,
     TOKEN_PROGRAM_ID,
     ASSOCIATED_TOKEN_PROGRAM_ID
 } from "@solana/spl-token";
@@ -27,12 +28,186 @@ describe("stablecoin_system", () => {
         [Buffer.from("global_state")],
         program.programId
     );
+    const [treasuryPda] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury")],
+        program.programId
+    );
 
     const [mintPda] = anchor.web3.PublicKey.findProgramAddressSync(
         [Buffer.from("mint")],
         program.programId
     );
 
+    const [roles] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("roles")],
+        program.programId
+    );
+
+    // Asserts that a transaction fails with the given program error code.
+    const expectError = async (code: string, call: Promise<unknown>) => {
+        let failed = false;
+        try {
+            await call;
+        } catch (e) {
+            failed = true;
+            assert.ok(JSON.stringify(e).includes(code), `Expected ${code}, got ${e}`);
+        }
+        assert.ok(failed, `Should have failed due to ${code}`);
+    };
+
+    // Mock feeds are keyed by seed; most tests share one feed.
+    const mockFeed = (seed: anchor.web3.PublicKey) => anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("mock_oracle"), seed.toBuffer()],
+        program.programId
+    )[0];
+    const mockFeedSeed = anchor.web3.Keypair.generate().publicKey;
+    const mockOracle = mockFeed(mockFeedSeed);
+
+    // Oracle source for legacy push feeds; the mock oracle build ignores it.
+    const legacyOracle = { kind: { pythLegacy: {} }, feedId: new Array(32).fill(0) };
+
+    const setMockPrice = async (price: number, seed = mockFeedSeed) => {
+        await program.methods.setMockPrice(seed, new anchor.BN(price * 1_000_000)).accounts({
+            globalState: globalState,
+            admin: provider.wallet.publicKey,
+        } as any).rpc();
+    };
+
+    // Lists a fresh 9-decimal collateral priced by the mock oracle PDA.
+    const listCollateral = async (mcr: number, ltr: number, penalty: number) => {
+        const mint = await createMint(
+            provider.connection,
+            (provider.wallet as any).payer,
+            provider.wallet.publicKey,
+            null,
+            9
+        );
+        const [config] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("collateral"), mint.toBuffer()],
+            program.programId
+        );
+        await program.methods.configureCollateral(
+            mint,
+            mockOracle,
+            legacyOracle,
+            new anchor.BN(mcr),
+            new anchor.BN(ltr),
+            new anchor.BN(penalty)
+        ).accounts({
+            collateralConfig: config,
+            oracleFeed: mockOracle,
+            authority: provider.wallet.publicKey,
+        } as any).rpc();
+        return { mint, config };
+    };
+
+    // Retries `call` while it fails with `code`, e.g. until a wait measured by the on-chain clock is over.
+    const retryWhile = async (code: string, call: () => Promise<unknown>) => {
+        for (let attempt = 0; ; attempt++) {
+            try {
+                return await call();
+            } catch (e) {
+                if (attempt >= 30 || !JSON.stringify(e).includes(code)) throw e;
+                await new Promise((resolve) => setTimeout(resolve, 500));
+            }
+        }
+    };
+
+    // Applies loosening params queued by a setter once the timelock lets it.
+    const applyCollateralParams = (config: anchor.web3.PublicKey) => retryWhile("TimelockNotElapsed", () => program.methods.applyCollateralParams().accounts({
+        collateralConfig: config,
+    } as any).rpc());
+
+    // Rolls the price cache of `mint` onto the current oracle price: drops its delay to the
+    // one-second floor, waits out the timelock on that reduction, then pokes twice a delay
+    // apart, so the spot price becomes the liquidation price.
+    const settleCachedPrice = async (mint: anchor.web3.PublicKey, config: anchor.web3.PublicKey) => {
+        const [priceCache] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("price_cache"), mint.toBuffer()],
+            program.programId
+        );
+        const cache = await program.account.priceCache.fetch(priceCache);
+        if (!cache.delaySecs.eqn(1) && !cache.pendingDelaySecs.eqn(1)) {
+            await program.methods.setPriceCacheDelay(new anchor.BN(1)).accounts({
+                priceCache: priceCache,
+                globalState: globalState,
+                authority: provider.wallet.publicKey,
+            } as any).rpc();
+        }
+        const poke = () => program.methods.poke().accounts({
+            priceCache: priceCache,
+            collateralConfig: config,
+            oracle: mockOracle,
+        } as any).rpc();
+        await retryWhile("PriceCacheNotReady", poke);
+        await retryWhile("PriceCacheNotReady", poke);
+        return priceCache;
+    };
+
+    const auctionOf = (position: anchor.web3.PublicKey) => anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("auction"), position.toBuffer()],
+        program.programId
+    )[0];
+
+    // Kicks `position` into an auction, with the provider wallet as keeper.
+    const kick = (position: anchor.web3.PublicKey, mint: anchor.web3.PublicKey) => program.methods.kick().accounts({
+        keeper: provider.wallet.publicKey,
+        position: position,
+        collateralMint: mint,
+    } as any).rpc();
+
+    // Buys up to `amount` whole tokens from the auction of `position`, paying at most `maxPrice` each.
+    const take = (taker: anchor.web3.Keypair, position: anchor.web3.PublicKey, mint: anchor.web3.PublicKey, amount: number, maxPrice: number, feeRecipient: anchor.web3.PublicKey | null = null) =>
+        program.methods.take(new anchor.BN(amount * 1_000_000_000), new anchor.BN(maxPrice * 1_000_000)).accounts({
+            taker: taker.publicKey,
+            auction: auctionOf(position),
+            position: position,
+            keeper: provider.wallet.publicKey,
+            collateralMint: mint,
+            feeRecipientAccount: feeRecipient,
+        } as any).signers([taker]).rpc();
+
+    // Funds `owner`, deposits `deposit` whole tokens of `mint` and mints `debt` USDT against them.
+    const openPosition = async (owner: anchor.web3.Keypair, mint: anchor.web3.PublicKey, deposit: number, debt: number) => {
+        const airdropTx = await provider.connection.requestAirdrop(owner.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+        await provider.connection.confirmTransaction(airdropTx);
+        const tokenAccount = await createAssociatedTokenAccount(
+            provider.connection,
+            (provider.wallet as any).payer,
+            mint,
+            owner.publicKey
+        );
+        await mintTo(
+            provider.connection,
+            (provider.wallet as any).payer,
+            mint,
+            tokenAccount,
+            provider.wallet.publicKey,
+            BigInt(deposit) * BigInt(1_000_000_000)
+        );
+        const [position] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("position"), owner.publicKey.toBuffer(), mint.toBuffer()],
+            program.programId
+        );
+        await program.methods.depositCollateral(new anchor.BN(deposit).mul(new anchor.BN(1_000_000_000))).accounts({
+            user: owner.publicKey,
+            collateralMint: mint,
+            userTokenAccount: tokenAccount,
+            position: position,
+        } as any).signers([owner]).rpc();
+        if (debt > 0) {
+            await program.methods.mintUsdt(new anchor.BN(debt * 1_000_000), null).accounts({
+                owner: owner.publicKey,
+                position: position,
+                oracle: mockOracle,
+                usdtMint: mintPda,
+                collateralMint: mint,
+            } as any).signers([owner]).rpc();
+        }
+        const usdtAccount = await anchor.utils.token.associatedAddress({ mint: mintPda, owner: owner.publicKey });
+        return { position, tokenAccount, usdtAccount };
+    };
+
     it("Is initialized!", async () => {
         // 1. Initialize
         try {
@@ -57,8 +232,8 @@ describe("stablecoin_system", () => {
             9 // 9 decimals like SOL
         );
 
-        // Mock Oracle Address (Random for now as we mock logic in utils.rs)
-        const oracle = anchor.web3.Keypair.generate().publicKey;
+        await setMockPrice(150);
+        const oracle = mockOracle;
 
         const [collateralConfig] = anchor.web3.PublicKey.findProgramAddressSync(
             [Buffer.from("collateral"), collateralMint.toBuffer()],
@@ -68,13 +243,15 @@ describe("stablecoin_system", () => {
         await program.methods.configureCollateral(
             collateralMint,
             oracle,
+            legacyOracle,
             new anchor.BN(150), // MCR 150%
             new anchor.BN(120), // LTR 120%
             new anchor.BN(10)   // Penalty 10%
         ).accounts({
             collateralConfig: collateralConfig,
+            oracleFeed: oracle,
             // globalState: globalState // inferred
-            admin: provider.wallet.publicKey,
+            authority: provider.wallet.publicKey,
         } as any).rpc();
 
         const configAccount = await program.account.collateralConfig.fetch(collateralConfig);
@@ -148,13 +325,14 @@ describe("stablecoin_system", () => {
         );
         const configData = await program.account.collateralConfig.fetch(collateralConfig);
 
-        // Mock Price is $150 in utils.rs.
+        // Mock price was set to $150 when listing.
         // Collateral: 5 SOL * $150 = $750 value
         // Max Debt (150% MCR): $750 / 1.5 = $500
         // Let's mint 400 USDT (assuming 6 decimals) -> 400 * 10^6 
 
         await program.methods.mintUsdt(
-            new anchor.BN(400 * 1_000_000)
+            new anchor.BN(400 * 1_000_000),
+            null
         ).accounts({
             owner: userBody.publicKey,
             position: position,
@@ -234,125 +412,1288 @@ describe("stablecoin_system", () => {
         assert.ok(positionAccount.collateralAmount.eq(new anchor.BN(4900000000)));
     });
 
-    it("Governance: Pauses and Unpauses System", async () => {
-        // 1. Pause
-        await program.methods.togglePause(true).accounts({
-            globalState: globalState,
-            admin: provider.wallet.publicKey,
+    it("Accrues stability fees into the surplus account", async () => {
+        const [position] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("position"), userBody.publicKey.toBuffer(), collateralMint.toBuffer()],
+            program.programId
+        );
+        const [collateralConfig] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("collateral"), collateralMint.toBuffer()],
+            program.programId
+        );
+        const [surplus] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("surplus")],
+            program.programId
+        );
+        const userUsdtAccount = await anchor.utils.token.associatedAddress({
+            mint: mintPda,
+            owner: userBody.publicKey
+        });
+
+        await expectError("InvalidStabilityFee", program.methods.setStabilityFee(new anchor.BN(1_000_000)).accounts({
+            collateralConfig: collateralConfig,
+            authority: provider.wallet.publicKey,
+        } as any).rpc());
+
+        // 50% APR so a few seconds of accrual is visible
+        await program.methods.setStabilityFee(new anchor.BN(5_000)).accounts({
+            collateralConfig: collateralConfig,
+            authority: provider.wallet.publicKey,
         } as any).rpc();
+        const debtBefore = (await program.account.position.fetch(position)).debtAmount;
+        await new Promise((resolve) => setTimeout(resolve, 3000));
 
-        const state = await program.account.globalState.fetch(globalState);
-        assert.ok(state.paused === true);
+        // Any touch accrues; burn a single unit
+        await program.methods.burnUsdt(new anchor.BN(1)).accounts({
+            owner: userBody.publicKey,
+            position: position,
+            usdtMint: mintPda,
+            userUsdtAccount: userUsdtAccount,
+            collateralMint: collateralMint,
+        } as any).signers([userBody]).rpc();
+        const positionAccount = await program.account.position.fetch(position);
+        console.log("Position Debt after accrual:", positionAccount.debtAmount.toString());
+        assert.ok(positionAccount.debtAmount.gt(debtBefore.subn(1)));
 
-        // 2. Try to deposit (should fail)
-        try {
-            const [position] = anchor.web3.PublicKey.findProgramAddressSync(
-                [Buffer.from("position"), userBody.publicKey.toBuffer(), collateralMint.toBuffer()],
-                program.programId
-            );
-            const [vault] = anchor.web3.PublicKey.findProgramAddressSync(
-                [Buffer.from("vault"), collateralMint.toBuffer()],
-                program.programId
-            );
+        const configAccount = await program.account.collateralConfig.fetch(collateralConfig);
+        assert.ok(configAccount.cumulativeRate.gt(new anchor.BN("1000000000000000000")));
 
-            await program.methods.depositCollateral(new anchor.BN(100)).accounts({
-                user: userBody.publicKey,
-                collateralMint: collateralMint,
-                userTokenAccount: userCollateralAccount,
-                vaultTokenAccount: vault,
-                position: position,
-                globalState: globalState, // Needed for check
-            } as any).signers([userBody]).rpc();
-            assert.fail("Should have failed due to Pause");
-        } catch (e) {
-            assert.ok(JSON.stringify(e).includes("Paused"));
-        }
+        await program.methods.collectStabilityFees().accounts({
+            payer: provider.wallet.publicKey,
+            collateralConfig: collateralConfig,
+            usdtMint: mintPda,
+            surplusAccount: surplus,
+        } as any).rpc();
+        const surplusAccount = await getAccount(provider.connection, surplus);
+        console.log("Surplus after collection:", surplusAccount.amount.toString());
+        assert.ok(surplusAccount.amount > BigInt(0));
 
-        // 3. Unpause
-        await program.methods.togglePause(false).accounts({
+        // Back to a zero fee so later assertions stay exact
+        await program.methods.setStabilityFee(new anchor.BN(0)).accounts({
+            collateralConfig: collateralConfig,
+            authority: provider.wallet.publicKey,
+        } as any).rpc();
+    });
+
+    it("Repays accrued debt before any fee collection", async () => {
+        const { mint, config } = await listCollateral(150, 120, 10);
+        const owner = anchor.web3.Keypair.generate();
+        const { position, usdtAccount } = await openPosition(owner, mint, 10, 600);
+        // Another borrower lends the owner enough to cover the fees
+        const lender = anchor.web3.Keypair.generate();
+        const { usdtAccount: lenderUsdt } = await openPosition(lender, mint, 10, 100);
+        await transfer(provider.connection, (provider.wallet as any).payer, lenderUsdt, usdtAccount, lender, BigInt(100 * 1_000_000));
+        const [surplus] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("surplus")], program.programId);
+        const surplusBefore = (await getAccount(provider.connection, surplus)).amount;
+
+        await program.methods.setStabilityFee(new anchor.BN(5_000)).accounts({
+            collateralConfig: config,
+            authority: provider.wallet.publicKey,
+        } as any).rpc();
+        await new Promise((resolve) => setTimeout(resolve, 3000));
+
+        // Repaying principal plus uncollected fees collects the fees first, then burns
+        await program.methods.burnUsdt(new anchor.BN(700 * 1_000_000)).accounts({
+            owner: owner.publicKey,
+            position: position,
+            usdtMint: mintPda,
+            userUsdtAccount: usdtAccount,
+            collateralMint: mint,
+        } as any).signers([owner]).rpc();
+        assert.ok((await program.account.position.fetch(position)).debtAmount.eqn(0));
+        assert.ok((await program.account.collateralConfig.fetch(config)).unclaimedFees.eqn(0));
+        assert.ok((await getAccount(provider.connection, surplus)).amount > surplusBefore);
+        await program.methods.checkSolvency().accounts({
             globalState: globalState,
-            admin: provider.wallet.publicKey,
+            usdtMint: mintPda,
         } as any).rpc();
+    });
 
-        const stateUnpaused = await program.account.globalState.fetch(globalState);
-        assert.ok(stateUnpaused.paused === false);
+    it("Uses MCR for minting and withdrawing, LTR for liquidation", async () => {
+        await expectError("InvalidLtr", listCollateral(150, 160, 10));
+
+        await setMockPrice(100);
+        const { mint, config } = await listCollateral(150, 120, 10);
+        const owner = anchor.web3.Keypair.generate();
+        // 10 tokens @ $100 = $1000 backing 600 USDT (CR 166%)
+        const { position, tokenAccount } = await openPosition(owner, mint, 10, 600);
+
+        // @ $80: $800 / 600 = 133%, between LTR and MCR
+        await setMockPrice(80);
+        await expectError("BelowMcr", program.methods.mintUsdt(new anchor.BN(1_000_000), null).accounts({
+            owner: owner.publicKey,
+            position: position,
+            oracle: mockOracle,
+            usdtMint: mintPda,
+            collateralMint: mint,
+        } as any).signers([owner]).rpc());
+        // Withdrawing 2 tokens would leave $640 / 600 = 106%, below LTR
+        await expectError("BelowLtr", program.methods.withdrawCollateral(new anchor.BN(2_000_000_000)).accounts({
+            owner: owner.publicKey,
+            position: position,
+            oracle: mockOracle,
+            collateralMint: mint,
+            userTokenAccount: tokenAccount,
+        } as any).signers([owner]).rpc());
+
+        await expectError("PositionSafe", kick(position, mint));
+
+        // @ $70: $700 / 600 = 116%, below LTR
+        await setMockPrice(70);
+        await settleCachedPrice(mint, config);
+        await kick(position, mint);
+        let positionAccount = await program.account.position.fetch(position);
+        assert.ok(positionAccount.debtAmount.eq(new anchor.BN(0)));
+        assert.ok(positionAccount.collateralAmount.eq(new anchor.BN(0)));
+        const auction = await program.account.auction.fetch(auctionOf(position));
+        assert.ok(auction.lot.eq(new anchor.BN(10_000_000_000)));
+        assert.ok(auction.tab.eq(new anchor.BN(660 * 1_000_000))); // 600 debt + 10% penalty
+        assert.ok(auction.top.eq(new anchor.BN(84 * 1_000_000)));  // $70 * 120% buffer
+
+        // Raising the 660 tab at <= $84 sells ~7.9 tokens; the rest returns to the position
+        const taker = anchor.web3.Keypair.generate();
+        await openPosition(taker, mint, 100, 1_000);
+        await take(taker, position, mint, 10, 84);
+        positionAccount = await program.account.position.fetch(position);
+        assert.ok(positionAccount.collateralAmount.gt(new anchor.BN(2_000_000_000)));
+        assert.ok(positionAccount.collateralAmount.lt(new anchor.BN(2_200_000_000)));
+        assert.isNull(await program.account.auction.fetchNullable(auctionOf(position)));
+
+        await setMockPrice(150);
     });
 
-    it("PSM: Swaps USDC for USDT and back", async () => {
-        // 1. Create Mock USDC Mint
-        const usdcMint = await createMint(
+    it("Rejects nonsensical collateral risk parameters", async () => {
+        await setMockPrice(150);
+        await expectError("InvalidMcr", listCollateral(100, 100, 10));
+        await expectError("InvalidMcr", listCollateral(5000, 120, 10));
+        await expectError("InvalidLtr", listCollateral(150, 100, 10));
+        await expectError("InvalidLtr", listCollateral(150, 151, 10));
+        await expectError("InvalidLiquidationPenalty", listCollateral(150, 120, 10_000));
+
+        // A program account that is not a mock price feed
+        const mint = await createMint(
             provider.connection,
             (provider.wallet as any).payer,
             provider.wallet.publicKey,
             null,
-            6
+            9
         );
-
-        // 2. Configure PSM
-        const [psmConfig] = anchor.web3.PublicKey.findProgramAddressSync(
-            [Buffer.from("psm"), usdcMint.toBuffer()],
+        const [config] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("collateral"), mint.toBuffer()],
             program.programId
         );
-        const [psmVault] = anchor.web3.PublicKey.findProgramAddressSync(
-            [Buffer.from("psm_vault"), usdcMint.toBuffer()],
+        await expectError("InvalidOracleFeed", program.methods.configureCollateral(
+            mint,
+            globalState,
+            legacyOracle,
+            new anchor.BN(150),
+            new anchor.BN(120),
+            new anchor.BN(10)
+        ).accounts({
+            collateralConfig: config,
+            oracleFeed: globalState,
+            authority: provider.wallet.publicKey,
+        } as any).rpc());
+
+        // The oracle account must match the oracle argument
+        await expectError("InvalidOracle", program.methods.configureCollateral(
+            mint,
+            mockOracle,
+            legacyOracle,
+            new anchor.BN(150),
+            new anchor.BN(120),
+            new anchor.BN(10)
+        ).accounts({
+            collateralConfig: config,
+            oracleFeed: globalState,
+            authority: provider.wallet.publicKey,
+        } as any).rpc());
+    });
+
+    it("Enforces per-collateral and global debt ceilings", async () => {
+        await setMockPrice(150);
+        const { mint, config } = await listCollateral(150, 120, 10);
+        const owner = anchor.web3.Keypair.generate();
+        // 10 tokens @ $150 = $1500, room for 1000 USDT at MCR
+        const { position, usdtAccount } = await openPosition(owner, mint, 10, 100);
+        const mintUsdt = (amount: number) => program.methods.mintUsdt(new anchor.BN(amount * 1_000_000), null).accounts({
+            owner: owner.publicKey,
+            position: position,
+            oracle: mockOracle,
+            usdtMint: mintPda,
+            collateralMint: mint,
+        } as any).signers([owner]).rpc();
+
+        await program.methods.setDebtCeiling(new anchor.BN(150 * 1_000_000)).accounts({
+            collateralConfig: config,
+            authority: provider.wallet.publicKey,
+        } as any).rpc();
+        await expectError("DebtCeilingExceeded", mintUsdt(60));
+        await mintUsdt(50);
+        let configAccount = await program.account.collateralConfig.fetch(config);
+        assert.ok(configAccount.totalDebt.eq(new anchor.BN(150 * 1_000_000)));
+
+        // Burning frees room under the ceiling
+        await program.methods.burnUsdt(new anchor.BN(50 * 1_000_000)).accounts({
+            owner: owner.publicKey,
+            position: position,
+            usdtMint: mintPda,
+            userUsdtAccount: usdtAccount,
+            collateralMint: mint,
+        } as any).signers([owner]).rpc();
+        configAccount = await program.account.collateralConfig.fetch(config);
+        assert.ok(configAccount.totalDebt.eq(new anchor.BN(100 * 1_000_000)));
+
+        // Global ceiling pinned at the current total blocks any further minting
+        const state = await program.account.globalState.fetch(globalState);
+        await program.methods.setGlobalDebtCeiling(state.totalDebt).accounts({
+            globalState: globalState,
+            authority: provider.wallet.publicKey,
+        } as any).rpc();
+        await expectError("GlobalDebtCeilingExceeded", mintUsdt(1));
+
+        await program.methods.setGlobalDebtCeiling(new anchor.BN("18446744073709551615")).accounts({
+            globalState: globalState,
+            authority: provider.wallet.publicKey,
+        } as any).rpc();
+        await mintUsdt(1);
+    });
+
+    it("Rejects mints and auction takes outside caller price bounds", async () => {
+        await setMockPrice(100);
+        const { mint, config } = await listCollateral(150, 120, 10);
+        const owner = anchor.web3.Keypair.generate();
+        const { position } = await openPosition(owner, mint, 10, 600);
+        const mintUsdt = (maxPrice: number) => program.methods.mintUsdt(new anchor.BN(1_000_000), new anchor.BN(maxPrice * 1_000_000)).accounts({
+            owner: owner.publicKey,
+            position: position,
+            oracle: mockOracle,
+            usdtMint: mintPda,
+            collateralMint: mint,
+        } as any).signers([owner]).rpc();
+        await expectError("PriceAboveMax", mintUsdt(99));
+        await mintUsdt(100);
+
+        // @ $70 the auction starts at $84 and only decays from there
+        await setMockPrice(70);
+        await settleCachedPrice(mint, config);
+        await kick(position, mint);
+        await expectError("SlippageExceeded", take(owner, position, mint, 1, 80));
+        await take(owner, position, mint, 1, 84);
+
+        await setMockPrice(150);
+    });
+
+    it("Kicks at the delayed price cache, which governance can freeze", async () => {
+        await setMockPrice(100);
+        const { mint, config } = await listCollateral(150, 120, 10);
+        const owner = anchor.web3.Keypair.generate();
+        const { position } = await openPosition(owner, mint, 10, 600);
+        const [priceCache] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("price_cache"), mint.toBuffer()],
             program.programId
         );
-
-        await program.methods.configurePsm(new anchor.BN(0)).accounts({
+        const poke = () => program.methods.poke().accounts({
+            priceCache: priceCache,
+            collateralConfig: config,
+            oracle: mockOracle,
+        } as any).rpc();
+        const setFrozen = (frozen: boolean) => program.methods.freezePriceCache(frozen).accounts({
+            priceCache: priceCache,
+            globalState: globalState,
             admin: provider.wallet.publicKey,
+        } as any).rpc();
+
+        // Seeded at listing; the default one-hour delay blocks an immediate poke
+        let cache = await program.account.priceCache.fetch(priceCache);
+        assert.ok(cache.currentPrice.eq(new anchor.BN(100 * 1_000_000)));
+        assert.ok(cache.delaySecs.eq(new anchor.BN(3_600)));
+        await expectError("PriceCacheNotReady", poke());
+        await expectError("InvalidPriceDelay", program.methods.setPriceCacheDelay(new anchor.BN(86_401)).accounts({
+            priceCache: priceCache,
             globalState: globalState,
-            tokenMint: usdcMint,
-            psmConfig: psmConfig,
-            psmVault: psmVault,
+            authority: provider.wallet.publicKey,
+        } as any).rpc());
+        await expectError("InvalidPriceDelay", program.methods.setPriceCacheDelay(new anchor.BN(0)).accounts({
+            priceCache: priceCache,
+            globalState: globalState,
+            authority: provider.wallet.publicKey,
+        } as any).rpc());
+        // Shortening the delay waits out the timelock
+        await program.methods.setPriceCacheDelay(new anchor.BN(1)).accounts({
+            priceCache: priceCache,
+            globalState: globalState,
+            authority: provider.wallet.publicKey,
         } as any).rpc();
+        cache = await program.account.priceCache.fetch(priceCache);
+        assert.ok(cache.delaySecs.eq(new anchor.BN(3_600)));
+        assert.ok(cache.pendingDelaySecs.eqn(1));
+        assert.ok(cache.delayEta.gtn(0));
 
-        // 3. Mint USDC to User
-        const userUsdcAccount = await createAssociatedTokenAccount(
-            provider.connection,
-            (provider.wallet as any).payer,
-            usdcMint,
-            userBody.publicKey
-        );
-        await mintTo(
+        // A $70 tick is queued, not served: the position is still valued at $100
+        await setMockPrice(70);
+        await retryWhile("PriceCacheNotReady", poke);
+        cache = await program.account.priceCache.fetch(priceCache);
+        assert.ok(cache.nextPrice.eq(new anchor.BN(70 * 1_000_000)));
+        await expectError("PositionSafe", kick(position, mint));
+
+        // Freezing drops the queued tick and stops pokes and kicks
+        await setFrozen(true);
+        await expectError("PriceCacheFrozen", poke());
+        await expectError("PriceCacheFrozen", kick(position, mint));
+        cache = await program.account.priceCache.fetch(priceCache);
+        assert.ok(cache.nextPrice.eq(new anchor.BN(100 * 1_000_000)));
+        await setFrozen(false);
+
+        // Once the tick survives a full hop it becomes the liquidation price
+        await retryWhile("PriceCacheNotReady", poke);
+        await expectError("PositionSafe", kick(position, mint));
+        await settleCachedPrice(mint, config);
+        await kick(position, mint);
+
+        await setMockPrice(150);
+    });
+
+    it("Auctions: takes partially and redoes stale auctions", async () => {
+        await setMockPrice(100);
+        const { mint, config } = await listCollateral(150, 120, 10);
+        const owner = anchor.web3.Keypair.generate();
+        const { position } = await openPosition(owner, mint, 10, 600);
+        const setAuctionParams = (params: Record<string, number>) => program.methods.setAuctionParams(
+            Object.fromEntries(Object.entries(params).map(([k, v]) => [k, new anchor.BN(v)])) as any
+        ).accounts({
+            collateralConfig: config,
+            authority: provider.wallet.publicKey,
+        } as any).rpc();
+        const params = { bufferBps: 12_000, durationSecs: 3_600, tailSecs: 1, cuspBps: 4_000, tip: 1_000_000, chipBps: 0, chost: 0 };
+        await expectError("InvalidAuctionParams", setAuctionParams({ ...params, bufferBps: 9_999 }));
+        await expectError("InvalidAuctionParams", setAuctionParams({ ...params, tailSecs: 3_601 }));
+        // Without a cusp the tail must end the auction before its price decays to zero
+        await expectError("InvalidAuctionParams", setAuctionParams({ ...params, cuspBps: 0, tailSecs: 3_600 }));
+        // A keeper tip pays out of surplus, so it waits out the timelock
+        await setAuctionParams(params);
+        let configAccount = await program.account.collateralConfig.fetch(config);
+        assert.ok(configAccount.auction.tip.eqn(0));
+        assert.ok(configAccount.pendingParamsEta.gtn(0));
+        await applyCollateralParams(config);
+        configAccount = await program.account.collateralConfig.fetch(config);
+        assert.ok(configAccount.auction.tip.eqn(1_000_000));
+        await expectError("NoPendingParamsChange", program.methods.applyCollateralParams().accounts({
+            collateralConfig: config,
+        } as any).rpc());
+
+        await setMockPrice(70);
+        await settleCachedPrice(mint, config);
+        await kick(position, mint);
+        await expectError("AuctionNotStale", program.methods.redo().accounts({
+            keeper: provider.wallet.publicKey,
+            auction: auctionOf(position),
+        } as any).rpc());
+
+        // A partial take leaves the rest of the lot and tab running
+        await take(owner, position, mint, 1, 84);
+        let auction = await program.account.auction.fetch(auctionOf(position));
+        assert.ok(auction.lot.eq(new anchor.BN(9_000_000_000)));
+        assert.ok(auction.tab.lt(new anchor.BN(660 * 1_000_000)));
+        assert.ok(auction.tab.gt(new anchor.BN(575 * 1_000_000)));
+
+        // Past its one-second tail the auction can only be redone, which pays the keeper's tip
+        await new Promise((resolve) => setTimeout(resolve, 2_500));
+        await expectError("AuctionNeedsRedo", take(owner, position, mint, 1, 84));
+        const keeperUsdt = await anchor.utils.token.associatedAddress({ mint: mintPda, owner: provider.wallet.publicKey });
+        const before = (await getAccount(provider.connection, keeperUsdt)).amount;
+        await program.methods.redo().accounts({
+            keeper: provider.wallet.publicKey,
+            auction: auctionOf(position),
+        } as any).rpc();
+        const after = (await getAccount(provider.connection, keeperUsdt)).amount;
+        assert.equal(after - before, BigInt(1_000_000));
+        auction = await program.account.auction.fetch(auctionOf(position));
+        assert.ok(auction.top.eq(new anchor.BN(84 * 1_000_000)));
+
+        // Below the minimum tab a redo pays no tip; raising it applies at once
+        await setAuctionParams({ ...params, chost: 1_000 * 1_000_000 });
+        await new Promise((resolve) => setTimeout(resolve, 2_500));
+        await program.methods.redo().accounts({
+            keeper: provider.wallet.publicKey,
+            auction: auctionOf(position),
+        } as any).rpc();
+        assert.equal((await getAccount(provider.connection, keeperUsdt)).amount, after);
+
+        await setMockPrice(150);
+    });
+
+    it("Auctions: partial kicks restore the target CR", async () => {
+        await setMockPrice(100);
+        const { mint, config } = await listCollateral(150, 120, 10);
+        const owners = [0, 1, 2].map(() => anchor.web3.Keypair.generate());
+        const positions: anchor.web3.PublicKey[] = [];
+        for (const owner of owners) {
+            positions.push((await openPosition(owner, mint, 10, 600)).position);
+        }
+        const setLiquidationParams = (closeFactorBps: number, dust: number, targetCr: number) => program.methods.setLiquidationParams(
+            new anchor.BN(closeFactorBps),
+            new anchor.BN(dust * 1_000_000),
+            new anchor.BN(targetCr)
+        ).accounts({
+            collateralConfig: config,
+            authority: provider.wallet.publicKey,
+        } as any).rpc();
+        await expectError("InvalidLiquidationParams", setLiquidationParams(0, 0, 150));
+        await expectError("InvalidLiquidationParams", setLiquidationParams(10_001, 0, 150));
+        await expectError("InvalidLiquidationParams", setLiquidationParams(10_000, 0, 110)); // not above 100% + penalty
+        await setLiquidationParams(10_000, 0, 150);
+
+        // @ $70: $700 / 600 = 116%. Repaying 500 with a 550 tab at $70 leaves 150%
+        await setMockPrice(70);
+        await settleCachedPrice(mint, config);
+        await kick(positions[0], mint);
+        let positionAccount = await program.account.position.fetch(positions[0]);
+        assert.ok(positionAccount.debtAmount.eq(new anchor.BN(100 * 1_000_000)));
+        assert.ok(positionAccount.collateralAmount.gt(new anchor.BN(1_660_000_000)));
+        assert.ok(positionAccount.collateralAmount.lt(new anchor.BN(1_670_000_000)));
+        let auction = await program.account.auction.fetch(auctionOf(positions[0]));
+        assert.ok(auction.tab.eq(new anchor.BN(550 * 1_000_000)));
+        assert.ok(auction.debt.eq(new anchor.BN(500 * 1_000_000)));
+
+        // Unsold collateral returns once the tab is raised, leaving the position at or above target
+        const taker = anchor.web3.Keypair.generate();
+        await openPosition(taker, mint, 100, 2_000);
+        await take(taker, positions[0], mint, 10, 84);
+        positionAccount = await program.account.position.fetch(positions[0]);
+        const value = positionAccount.collateralAmount.mul(new anchor.BN(70)).div(new anchor.BN(1_000));
+        assert.ok(value.mul(new anchor.BN(100)).gte(positionAccount.debtAmount.mul(new anchor.BN(150))));
+
+        // A 100 USDT remainder is below 200 USDT of dust, so the whole debt is kicked. Raising
+        // the dust kicks more, so it waits out the timelock.
+        await setLiquidationParams(10_000, 200, 150);
+        await applyCollateralParams(config);
+        await kick(positions[1], mint);
+        positionAccount = await program.account.position.fetch(positions[1]);
+        assert.ok(positionAccount.debtAmount.eq(new anchor.BN(0)));
+        assert.ok(positionAccount.collateralAmount.eq(new anchor.BN(0)));
+
+        // A 50% close factor caps the kick at 300 of the 600 debt
+        await setLiquidationParams(5_000, 0, 0);
+        await applyCollateralParams(config);
+        await kick(positions[2], mint);
+        positionAccount = await program.account.position.fetch(positions[2]);
+        assert.ok(positionAccount.debtAmount.eq(new anchor.BN(300 * 1_000_000)));
+        auction = await program.account.auction.fetch(auctionOf(positions[2]));
+        assert.ok(auction.tab.eq(new anchor.BN(330 * 1_000_000)));
+        assert.ok(auction.lot.eq(new anchor.BN(5_000_000_000)));
+
+        await setMockPrice(150);
+    });
+
+    it("Recognizes auction shortfalls as bad debt and covers them from surplus, then the backstop", async () => {
+        await setMockPrice(100);
+        const { mint, config } = await listCollateral(150, 120, 10);
+        const owner = anchor.web3.Keypair.generate();
+        const { position } = await openPosition(owner, mint, 10, 600);
+        const taker = anchor.web3.Keypair.generate();
+        await openPosition(taker, mint, 100, 1_000);
+        await expectError("NoBadDebt", program.methods.recognizeBadDebt().accounts({
+            payer: provider.wallet.publicKey,
+            position: position,
+        } as any).rpc());
+
+        const [systemDebt] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("system_debt")], program.programId);
+        const badDebtBefore = (await program.account.systemDebt.fetchNullable(systemDebt))?.badDebt ?? new anchor.BN(0);
+
+        // @ $10 the whole lot raises ~120 of the 600 debt; the rest is bad debt
+        await setMockPrice(10);
+        await settleCachedPrice(mint, config);
+        await kick(position, mint);
+        await take(taker, position, mint, 10, 12);
+        assert.isNull(await program.account.auction.fetchNullable(auctionOf(position)));
+        let ledger = await program.account.systemDebt.fetch(systemDebt);
+        const shortfall = ledger.badDebt.sub(badDebtBefore);
+        assert.ok(shortfall.gt(new anchor.BN(470 * 1_000_000)));
+        assert.ok(shortfall.lt(new anchor.BN(490 * 1_000_000)));
+
+        // Governance funds a 50 USDT backstop and lets up to 40 of it be burned
+        await expectError("Unauthorized", program.methods.setBackstopLimit(new anchor.BN(40 * 1_000_000)).accounts({
+            admin: taker.publicKey,
+        } as any).signers([taker]).rpc());
+        await program.methods.setBackstopLimit(new anchor.BN(40 * 1_000_000)).accounts({
+            admin: provider.wallet.publicKey,
+        } as any).rpc();
+        const [backstop] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("backstop")], program.programId);
+        const [surplus] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("surplus")], program.programId);
+        const takerUsdt = await anchor.utils.token.associatedAddress({ mint: mintPda, owner: taker.publicKey });
+        await transfer(provider.connection, (provider.wallet as any).payer, takerUsdt, backstop, taker, BigInt(50 * 1_000_000));
+
+        ledger = await program.account.systemDebt.fetch(systemDebt);
+        const badDebt = BigInt(ledger.badDebt.toString());
+        const surplusBefore = (await getAccount(provider.connection, surplus)).amount;
+        const backstopBefore = (await getAccount(provider.connection, backstop)).amount;
+        await program.methods.coverBadDebt().accounts({} as any).rpc();
+
+        const fromSurplus = badDebt < surplusBefore ? badDebt : surplusBefore;
+        const remaining = badDebt - fromSurplus;
+        const fromBackstop = remaining < BigInt(40 * 1_000_000) ? remaining : BigInt(40 * 1_000_000);
+        assert.equal(surplusBefore - (await getAccount(provider.connection, surplus)).amount, fromSurplus);
+        assert.equal(backstopBefore - (await getAccount(provider.connection, backstop)).amount, fromBackstop);
+        ledger = await program.account.systemDebt.fetch(systemDebt);
+        assert.equal(BigInt(ledger.badDebt.toString()), remaining - fromBackstop);
+        assert.equal(BigInt(ledger.backstopLimit.toString()), BigInt(40 * 1_000_000) - fromBackstop);
+
+        await setMockPrice(150);
+    });
+
+    it("Treasury: splits liquidation penalties and limits surplus withdrawals", async () => {
+        await setMockPrice(100);
+        const { mint, config } = await listCollateral(150, 120, 10);
+        const owner = anchor.web3.Keypair.generate();
+        const { position } = await openPosition(owner, mint, 10, 600);
+        const taker = anchor.web3.Keypair.generate();
+        await openPosition(taker, mint, 100, 2_000);
+        const takerUsdt = await anchor.utils.token.associatedAddress({ mint: mintPda, owner: taker.publicKey });
+        const [surplus] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("surplus")], program.programId);
+        const [backstop] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("backstop")], program.programId);
+        const [systemDebt] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("system_debt")], program.programId);
+
+        // Clear any bad debt left by earlier tests through the backstop
+        const outstanding = (await program.account.systemDebt.fetch(systemDebt)).badDebt;
+        await program.methods.setBackstopLimit(outstanding).accounts({ admin: provider.wallet.publicKey } as any).rpc();
+        await transfer(provider.connection, (provider.wallet as any).payer, takerUsdt, backstop, taker, BigInt(outstanding.toString()));
+        await program.methods.coverBadDebt().accounts({} as any).rpc();
+        assert.ok((await program.account.systemDebt.fetch(systemDebt)).badDebt.eqn(0));
+
+        const recipient = await createAssociatedTokenAccount(
             provider.connection,
             (provider.wallet as any).payer,
-            usdcMint,
-            userUsdcAccount,
-            provider.wallet.publicKey,
-            1000 * 1_000_000 // 1000 USDC
+            mintPda,
+            anchor.web3.Keypair.generate().publicKey
         );
+        const params = {
+            surplusBps: new anchor.BN(5_000),
+            liquidatorBps: new anchor.BN(2_000),
+            feeRecipientBps: new anchor.BN(3_000),
+            bufferFloor: new anchor.BN(0),
+            windowSecs: new anchor.BN(86_400),
+            windowLimit: new anchor.BN(5 * 1_000_000),
+        };
+        const configureTreasury = (p: typeof params, feeRecipient: anchor.web3.PublicKey | null, signer?: anchor.web3.Keypair) => {
+            const builder = program.methods.configureTreasury(p).accounts({
+                feeRecipient: feeRecipient,
+                admin: (signer ?? (provider.wallet as any).payer).publicKey,
+            } as any);
+            return signer ? builder.signers([signer]).rpc() : builder.rpc();
+        };
+        await expectError("InvalidTreasuryParams", configureTreasury({ ...params, surplusBps: new anchor.BN(4_000) }, recipient));
+        await expectError("MissingFeeRecipient", configureTreasury(params, null));
+        await expectError("Unauthorized", configureTreasury(params, recipient, taker));
 
-        // 4. Swap USDC -> USDT
-        const userUsdtAccount = await anchor.utils.token.associatedAddress({
-            mint: mintPda,
-            owner: userBody.publicKey
-        });
+        // Raising the withdrawal limit and adding a fee share wait out the timelock
+        await configureTreasury(params, recipient);
+        let treasury = await program.account.treasury.fetch(treasuryPda);
+        assert.ok(treasury.params.windowLimit.eqn(0));
+        assert.ok(treasury.pendingEta.gtn(0));
+        await new Promise((resolve) => setTimeout(resolve, 2000));
+        await program.methods.applyTreasuryParams().accounts({} as any).rpc();
+        treasury = await program.account.treasury.fetch(treasuryPda);
+        assert.ok(treasury.params.windowLimit.eq(params.windowLimit));
+        await expectError("NoPendingTreasuryChange", program.methods.applyTreasuryParams().accounts({} as any).rpc());
 
-        await program.methods.swapUsdcToUsdt(new anchor.BN(500 * 1_000_000)).accounts({
-            user: userBody.publicKey,
-            psmConfig: psmConfig,
-            tokenMint: usdcMint,
-            psmVault: psmVault,
-            userTokenAccount: userUsdcAccount,
-            usdtMint: mintPda,
-            userUsdtAccount: userUsdtAccount,
-            globalState: globalState,
-        } as any).signers([userBody]).rpc();
+        // @ $70 the 660 tab is raised in full: 600 burned, the 60 penalty split 30 / 12 / 18
+        await setMockPrice(70);
+        await settleCachedPrice(mint, config);
+        await kick(position, mint);
+        const takerBefore = (await getAccount(provider.connection, takerUsdt)).amount;
+        const surplusBefore = (await getAccount(provider.connection, surplus)).amount;
+        await expectError("MissingFeeRecipient", take(taker, position, mint, 10, 84));
+        await take(taker, position, mint, 10, 84, recipient);
+        assert.equal(takerBefore - (await getAccount(provider.connection, takerUsdt)).amount, BigInt(648 * 1_000_000));
+        assert.equal((await getAccount(provider.connection, surplus)).amount - surplusBefore, BigInt(30 * 1_000_000));
+        assert.equal((await getAccount(provider.connection, recipient)).amount, BigInt(18 * 1_000_000));
 
-        // Check balances not easily possible without connection fetch, but if it didn't fail it's good.
-        const psmConfigAccount = await program.account.psmConfig.fetch(psmConfig);
+        // Withdrawals are capped at 5 USDT per window
+        const withdraw = (amount: number) => program.methods.withdrawSurplus(new anchor.BN(amount * 1_000_000)).accounts({
+            destination: recipient,
+            admin: provider.wallet.publicKey,
+        } as any).rpc();
+        await expectError("TreasuryWithdrawalExceeded", withdraw(6));
+        await withdraw(5);
+        await expectError("TreasuryWithdrawalExceeded", withdraw(1));
+        assert.equal((await getAccount(provider.connection, recipient)).amount, BigInt(23 * 1_000_000));
+
+        await configureTreasury({ ...params, surplusBps: new anchor.BN(10_000), liquidatorBps: new anchor.BN(0), feeRecipientBps: new anchor.BN(0), windowLimit: new anchor.BN(0) }, null);
+        await setMockPrice(150);
+    });
+
+    it("Configures oracle staleness and confidence bounds", async () => {
+        const { config } = await listCollateral(150, 120, 10);
+        let configAccount = await program.account.collateralConfig.fetch(config);
+        assert.ok(configAccount.maxStalenessSecs.eq(new anchor.BN(60)));
+        assert.ok(configAccount.maxConfidenceBps.eq(new anchor.BN(200)));
+
+        const setOracleParams = (staleness: number, confidence: number) => program.methods.setOracleParams(
+            new anchor.BN(staleness),
+            new anchor.BN(confidence)
+        ).accounts({
+            collateralConfig: config,
+            authority: provider.wallet.publicKey,
+        } as any).rpc();
+        await expectError("InvalidOracleParams", setOracleParams(0, 100));
+        await expectError("InvalidOracleParams", setOracleParams(30, 10_001));
+        await setOracleParams(30, 50);
+
+        configAccount = await program.account.collateralConfig.fetch(config);
+        assert.ok(configAccount.maxStalenessSecs.eq(new anchor.BN(30)));
+        assert.ok(configAccount.maxConfidenceBps.eq(new anchor.BN(50)));
+    });
+
+    it("Medianizes extra oracles and trips on deviation", async () => {
+        await setMockPrice(100);
+        const { mint, config } = await listCollateral(150, 120, 10);
+        const owner = anchor.web3.Keypair.generate();
+        const { position } = await openPosition(owner, mint, 10, 100);
+
+        // Two governance-posted feeds around the $100 mock price
+        const postPrice = async (id: number, price: number) => {
+            const feedId = new Array(32).fill(id);
+            const [account] = anchor.web3.PublicKey.findProgramAddressSync(
+                [Buffer.from("governance_price"), Buffer.from(feedId)],
+                program.programId
+            );
+            await program.methods.postGovernancePrice(feedId, new anchor.BN(price * 1_000_000)).accounts({
+                governancePrice: account,
+                globalState: globalState,
+                authority: provider.wallet.publicKey,
+            } as any).rpc();
+            return { oracle: account, source: { kind: { governance: {} }, feedId } };
+        };
+        const feedA = await postPrice(1, 101);
+        const feedB = await postPrice(2, 99);
+        const remainingAccounts = [feedA, feedB].map((f) => ({ pubkey: f.oracle, isSigner: false, isWritable: false }));
+
+        // Extra oracles go through the timelock like any other risk parameter
+        const configAccount = await program.account.collateralConfig.fetch(config);
+        const [pendingChange] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("pending_change"), mint.toBuffer()],
+            program.programId
+        );
+        const queueOracles = (feeds: typeof feedA[]) => program.methods.queueChange(
+            configAccount.oracle,
+            configAccount.oracleSource,
+            configAccount.mcr,
+            configAccount.ltr,
+            configAccount.liquidationPenalty,
+            feeds,
+            new anchor.BN(500)
+        ).accounts({
+            pendingChange: pendingChange,
+            collateralConfig: config,
+            oracleFeed: configAccount.oracle,
+            authority: provider.wallet.publicKey,
+        } as any).remainingAccounts(remainingAccounts).rpc();
+        await expectError("InvalidOracle", queueOracles([feedA, feedA]));
+        // Governance prices may only back up market feeds, never replace them
+        await expectError("GovernancePrimaryOracle", program.methods.queueChange(
+            feedA.oracle,
+            feedA.source,
+            configAccount.mcr,
+            configAccount.ltr,
+            configAccount.liquidationPenalty,
+            [],
+            new anchor.BN(500)
+        ).accounts({
+            pendingChange: pendingChange,
+            collateralConfig: config,
+            oracleFeed: feedA.oracle,
+            authority: provider.wallet.publicKey,
+        } as any).rpc());
+        const unlisted = await createMint(provider.connection, (provider.wallet as any).payer, provider.wallet.publicKey, null, 9);
+        await expectError("GovernancePrimaryOracle", program.methods.configureCollateral(
+            unlisted,
+            feedA.oracle,
+            feedA.source,
+            new anchor.BN(150),
+            new anchor.BN(120),
+            new anchor.BN(10)
+        ).accounts({
+            collateralConfig: anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("collateral"), unlisted.toBuffer()], program.programId)[0],
+            oracleFeed: feedA.oracle,
+            authority: provider.wallet.publicKey,
+        } as any).rpc());
+        await queueOracles([feedA, feedB]);
+        await new Promise((resolve) => setTimeout(resolve, 2000));
+        await program.methods.executeChange().accounts({
+            pendingChange: pendingChange,
+            collateralConfig: config,
+            authority: provider.wallet.publicKey,
+        } as any).rpc();
+
+        const mintUsdt = (accounts: typeof remainingAccounts) => program.methods.mintUsdt(new anchor.BN(1_000_000), null).accounts({
+            owner: owner.publicKey,
+            position: position,
+            oracle: mockOracle,
+            usdtMint: mintPda,
+            collateralMint: mint,
+        } as any).remainingAccounts(accounts).signers([owner]).rpc();
+        await mintUsdt(remainingAccounts);
+        await expectError("MissingOracleAccount", mintUsdt(remainingAccounts.slice(0, 1)));
+
+        // $80 is 20% below the new $100 median
+        await postPrice(2, 80);
+        await expectError("OracleDeviationExceeded", mintUsdt(remainingAccounts));
+
+        await setMockPrice(150);
+    });
+
+    it("Keeps mock prices per feed with no fallback price", async () => {
+        const seedA = anchor.web3.Keypair.generate().publicKey;
+        const seedB = anchor.web3.Keypair.generate().publicKey;
+        await setMockPrice(2, seedA);
+        await setMockPrice(3, seedB);
+        const feedA = await program.account.mockPriceAccount.fetch(mockFeed(seedA));
+        const feedB = await program.account.mockPriceAccount.fetch(mockFeed(seedB));
+        assert.ok(feedA.price.eq(new anchor.BN(2 * 1_000_000)));
+        assert.ok(feedB.price.eq(new anchor.BN(3 * 1_000_000)));
+        assert.ok(feedA.seed.equals(seedA));
+
+        // An account that was never set is not a feed
+        const mint = await createMint(
+            provider.connection,
+            (provider.wallet as any).payer,
+            provider.wallet.publicKey,
+            null,
+            9
+        );
+        const unset = mockFeed(anchor.web3.Keypair.generate().publicKey);
+        await expectError("InvalidOracleFeed", program.methods.configureCollateral(
+            mint,
+            unset,
+            legacyOracle,
+            new anchor.BN(150),
+            new anchor.BN(120),
+            new anchor.BN(10)
+        ).accounts({
+            oracleFeed: unset,
+            authority: provider.wallet.publicKey,
+        } as any).rpc());
+    });
+
+    it("Emits events with before/after values and the price used", async () => {
+        // Decodes the Anchor events logged by a confirmed transaction.
+        const eventsOf = async (signature: string) => {
+            await provider.connection.confirmTransaction(signature, "confirmed");
+            const tx = await provider.connection.getTransaction(signature, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+            const parser = new anchor.EventParser(program.programId, new anchor.BorshCoder(program.idl));
+            return Array.from(parser.parseLogs(tx.meta.logMessages));
+        };
+
+        await setMockPrice(100);
+        const { mint } = await listCollateral(150, 120, 10);
+        const owner = anchor.web3.Keypair.generate();
+        const { position } = await openPosition(owner, mint, 10, 100);
+
+        const signature = await program.methods.mintUsdt(new anchor.BN(50 * 1_000_000), null).accounts({
+            owner: owner.publicKey,
+            position: position,
+            oracle: mockOracle,
+            usdtMint: mintPda,
+            collateralMint: mint,
+        } as any).signers([owner]).rpc();
+        const [minted] = await eventsOf(signature);
+        assert.equal(minted.name, "usdtMinted");
+        assert.ok(minted.data.owner.equals(owner.publicKey));
+        assert.ok(minted.data.oldDebt.gte(new anchor.BN(100 * 1_000_000)));
+        assert.ok(minted.data.newDebt.eq(minted.data.oldDebt.add(new anchor.BN(50 * 1_000_000))));
+        assert.ok(minted.data.price.eq(new anchor.BN(100 * 1_000_000)));
+
+        const pause = (paused: boolean) => program.methods.togglePause(paused).accounts({
+            globalState: globalState,
+            authority: provider.wallet.publicKey,
+        } as any).rpc();
+        const [paused] = await eventsOf(await pause(true));
+        await pause(false);
+        assert.equal(paused.name, "pauseUpdated");
+        assert.equal(paused.data.oldPaused, false);
+        assert.equal(paused.data.newPaused, true);
+
+        await setMockPrice(150);
+    });
+
+    it("Governance: Pauses and Unpauses System", async () => {
+        // 1. Pause
+        await program.methods.togglePause(true).accounts({
+            globalState: globalState,
+            authority: provider.wallet.publicKey,
+        } as any).rpc();
+
+        const state = await program.account.globalState.fetch(globalState);
+        assert.ok(state.paused === true);
+
+        // 2. Try to deposit (should fail)
+        try {
+            const [position] = anchor.web3.PublicKey.findProgramAddressSync(
+                [Buffer.from("position"), userBody.publicKey.toBuffer(), collateralMint.toBuffer()],
+                program.programId
+            );
+            const [vault] = anchor.web3.PublicKey.findProgramAddressSync(
+                [Buffer.from("vault"), collateralMint.toBuffer()],
+                program.programId
+            );
+
+            await program.methods.depositCollateral(new anchor.BN(100)).accounts({
+                user: userBody.publicKey,
+                collateralMint: collateralMint,
+                userTokenAccount: userCollateralAccount,
+                vaultTokenAccount: vault,
+                position: position,
+                globalState: globalState, // Needed for check
+            } as any).signers([userBody]).rpc();
+            assert.fail("Should have failed due to Pause");
+        } catch (e) {
+            assert.ok(JSON.stringify(e).includes("Paused"));
+        }
+
+        // 3. Unpause
+        await program.methods.togglePause(false).accounts({
+            globalState: globalState,
+            authority: provider.wallet.publicKey,
+        } as any).rpc();
+
+        const stateUnpaused = await program.account.globalState.fetch(globalState);
+        assert.ok(stateUnpaused.paused === false);
+    });
+
+    it("Governance: Rejects non-admin signers", async () => {
+        const intruder = anchor.web3.Keypair.generate();
+        const airdropTx = await provider.connection.requestAirdrop(intruder.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+        await provider.connection.confirmTransaction(airdropTx);
+
+        // Pause
+        await expectError("Unauthorized", program.methods.togglePause(true).accounts({
+            globalState: globalState,
+            authority: intruder.publicKey,
+        } as any).signers([intruder]).rpc());
+
+        // Freeze
+        const [position] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("position"), userBody.publicKey.toBuffer(), collateralMint.toBuffer()],
+            program.programId
+        );
+        await expectError("Unauthorized", program.methods.toggleFreeze(true).accounts({
+            globalState: globalState,
+            position: position,
+            authority: intruder.publicKey,
+        } as any).signers([intruder]).rpc());
+
+        // Configure Collateral (attempt to rewrite MCR)
+        const [collateralConfig] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("collateral"), collateralMint.toBuffer()],
+            program.programId
+        );
+        const rogueOracle = anchor.web3.Keypair.generate().publicKey;
+        await expectError("Unauthorized", program.methods.configureCollateral(
+            collateralMint,
+            rogueOracle,
+            legacyOracle,
+            new anchor.BN(1000),
+            new anchor.BN(900),
+            new anchor.BN(50)
+        ).accounts({
+            collateralConfig: collateralConfig,
+            oracleFeed: rogueOracle,
+            authority: intruder.publicKey,
+        } as any).signers([intruder]).rpc());
+
+        // Configure PSM
+        const psmMint = await createMint(
+            provider.connection,
+            (provider.wallet as any).payer,
+            provider.wallet.publicKey,
+            null,
+            6
+        );
+        await expectError("Unauthorized", program.methods.configurePsm(legacyOracle, new anchor.BN(0), new anchor.BN(0)).accounts({
+            authority: intruder.publicKey,
+            globalState: globalState,
+            tokenMint: psmMint,
+            oracle: anchor.web3.Keypair.generate().publicKey,
+        } as any).signers([intruder]).rpc());
+
+        // Set Mock Price
+        await expectError("Unauthorized", program.methods.setMockPrice(mockFeedSeed, new anchor.BN(1)).accounts({
+            globalState: globalState,
+            admin: intruder.publicKey,
+        } as any).signers([intruder]).rpc());
+
+        // State is untouched
+        const state = await program.account.globalState.fetch(globalState);
+        assert.ok(state.paused === false);
+        const positionAccount = await program.account.position.fetch(position);
+        assert.ok(positionAccount.isFrozen === false);
+        const configAccount = await program.account.collateralConfig.fetch(collateralConfig);
+        assert.ok(configAccount.mcr.eq(new anchor.BN(150)));
+    });
+
+    it("Governance: Delegates and revokes operational roles", async () => {
+        const pauser = anchor.web3.Keypair.generate();
+        const airdropTx = await provider.connection.requestAirdrop(pauser.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+        await provider.connection.confirmTransaction(airdropTx);
+
+        // Only the admin may grant roles
+        await expectError("Unauthorized", program.methods.grantRole({ pauser: {} }, pauser.publicKey).accounts({
+            globalState: globalState,
+            roles: roles,
+            admin: pauser.publicKey,
+        } as any).signers([pauser]).rpc());
+
+        await program.methods.grantRole({ pauser: {} }, pauser.publicKey).accounts({
+            globalState: globalState,
+            roles: roles,
+            admin: provider.wallet.publicKey,
+        } as any).rpc();
+        const rolesAccount = await program.account.roles.fetch(roles);
+        assert.ok(rolesAccount.pauser.equals(pauser.publicKey));
+
+        // The pauser can pause and unpause
+        await program.methods.togglePause(true).accounts({
+            globalState: globalState,
+            authority: pauser.publicKey,
+        } as any).signers([pauser]).rpc();
+        assert.ok((await program.account.globalState.fetch(globalState)).paused === true);
+        await program.methods.togglePause(false).accounts({
+            globalState: globalState,
+            authority: pauser.publicKey,
+        } as any).signers([pauser]).rpc();
+
+        // ...but holds no other role
+        const [position] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("position"), userBody.publicKey.toBuffer(), collateralMint.toBuffer()],
+            program.programId
+        );
+        await expectError("Unauthorized", program.methods.toggleFreeze(true).accounts({
+            globalState: globalState,
+            position: position,
+            authority: pauser.publicKey,
+        } as any).signers([pauser]).rpc());
+
+        // After revocation the key is rejected
+        await program.methods.revokeRole({ pauser: {} }).accounts({
+            globalState: globalState,
+            roles: roles,
+            admin: provider.wallet.publicKey,
+        } as any).rpc();
+        await expectError("Unauthorized", program.methods.togglePause(true).accounts({
+            globalState: globalState,
+            authority: pauser.publicKey,
+        } as any).signers([pauser]).rpc());
+    });
+
+    it("Governance: Transfers admin in two steps", async () => {
+        const newAdmin = anchor.web3.Keypair.generate();
+        const stranger = anchor.web3.Keypair.generate();
+
+        // Propose, then cancel
+        await program.methods.proposeAdmin(newAdmin.publicKey).accounts({
+            globalState: globalState,
+            admin: provider.wallet.publicKey,
+        } as any).rpc();
+        let state = await program.account.globalState.fetch(globalState);
+        assert.ok(state.pendingAdmin.equals(newAdmin.publicKey));
+
+        await program.methods.cancelAdminTransfer().accounts({
+            globalState: globalState,
+            admin: provider.wallet.publicKey,
+        } as any).rpc();
+        state = await program.account.globalState.fetch(globalState);
+        assert.ok(state.pendingAdmin.equals(anchor.web3.PublicKey.default));
+
+        await expectError("NoPendingAdmin", program.methods.acceptAdmin().accounts({
+            globalState: globalState,
+            newAdmin: newAdmin.publicKey,
+        } as any).signers([newAdmin]).rpc());
+
+        // Propose again; only the proposed key may accept
+        await program.methods.proposeAdmin(newAdmin.publicKey).accounts({
+            globalState: globalState,
+            admin: provider.wallet.publicKey,
+        } as any).rpc();
+        await expectError("Unauthorized", program.methods.acceptAdmin().accounts({
+            globalState: globalState,
+            newAdmin: stranger.publicKey,
+        } as any).signers([stranger]).rpc());
+
+        await program.methods.acceptAdmin().accounts({
+            globalState: globalState,
+            newAdmin: newAdmin.publicKey,
+        } as any).signers([newAdmin]).rpc();
+        state = await program.account.globalState.fetch(globalState);
+        assert.ok(state.admin.equals(newAdmin.publicKey));
+        assert.ok(state.pendingAdmin.equals(anchor.web3.PublicKey.default));
+
+        // Hand admin back so later tests keep using the provider wallet
+        await program.methods.proposeAdmin(provider.wallet.publicKey).accounts({
+            globalState: globalState,
+            admin: newAdmin.publicKey,
+        } as any).signers([newAdmin]).rpc();
+        await program.methods.acceptAdmin().accounts({
+            globalState: globalState,
+            newAdmin: provider.wallet.publicKey,
+        } as any).rpc();
+        state = await program.account.globalState.fetch(globalState);
+        assert.ok(state.admin.equals(provider.wallet.publicKey));
+    });
+
+    it("Governance: Timelocks collateral parameter changes", async () => {
+        const [collateralConfig] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("collateral"), collateralMint.toBuffer()],
+            program.programId
+        );
+        const [pendingChange] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("pending_change"), collateralMint.toBuffer()],
+            program.programId
+        );
+        const configBefore = await program.account.collateralConfig.fetch(collateralConfig);
+
+        // Direct reconfiguration of a listed collateral is refused
+        await expectError("TimelockRequired", program.methods.configureCollateral(
+            collateralMint,
+            configBefore.oracle,
+            legacyOracle,
+            new anchor.BN(110),
+            new anchor.BN(105),
+            new anchor.BN(10)
+        ).accounts({
+            collateralConfig: collateralConfig,
+            oracleFeed: configBefore.oracle,
+            authority: provider.wallet.publicKey,
+        } as any).rpc());
+
+        const setTimelockDelay = (delay: number) => program.methods.setTimelockDelay(new anchor.BN(delay)).accounts({
+            globalState: globalState,
+            admin: provider.wallet.publicKey,
+        } as any).rpc();
+        const queue = () => program.methods.queueChange(configBefore.oracle, legacyOracle, new anchor.BN(150), new anchor.BN(120), new anchor.BN(12), [], configBefore.maxDeviationBps).accounts({
+            pendingChange: pendingChange,
+            collateralConfig: collateralConfig,
+            oracleFeed: configBefore.oracle,
+            authority: provider.wallet.publicKey,
+        } as any).rpc();
+        const execute = () => program.methods.executeChange().accounts({
+            pendingChange: pendingChange,
+            collateralConfig: collateralConfig,
+            authority: provider.wallet.publicKey,
+        } as any).rpc();
+        await expectError("InvalidTimelockDelay", setTimelockDelay(0));
+
+        // A longer delay applies at once: the queued change cannot execute yet, but can be cancelled
+        await setTimelockDelay(3);
+        await queue();
+        await expectError("TimelockNotElapsed", execute());
+        await program.methods.cancelChange().accounts({
+            pendingChange: pendingChange,
+            collateralConfig: collateralConfig,
+            authority: provider.wallet.publicKey,
+        } as any).rpc();
+        assert.ok((await provider.connection.getAccountInfo(pendingChange)) === null);
+
+        // A shorter delay waits out the current one, so a change queued now still waits 3s
+        await setTimelockDelay(1);
+        const state = await program.account.globalState.fetch(globalState);
+        assert.ok(state.timelockDelay.eqn(3));
+        assert.ok(state.pendingTimelockDelay.eqn(1));
+        await queue();
+        await new Promise((resolve) => setTimeout(resolve, 1500));
+        await expectError("TimelockNotElapsed", execute());
+        await new Promise((resolve) => setTimeout(resolve, 2000));
+        await execute();
+
+        const configAfter = await program.account.collateralConfig.fetch(collateralConfig);
+        assert.ok(configAfter.liquidationPenalty.eq(new anchor.BN(12)));
+        assert.ok(configAfter.mcr.eq(configBefore.mcr));
+    });
+
+    it("Governance: Routes admin actions through an M-of-N multisig", async () => {
+        const owners = [anchor.web3.Keypair.generate(), anchor.web3.Keypair.generate(), anchor.web3.Keypair.generate()];
+        for (const owner of owners) {
+            const airdropTx = await provider.connection.requestAirdrop(owner.publicKey, anchor.web3.LAMPORTS_PER_SOL);
+            await provider.connection.confirmTransaction(airdropTx);
+        }
+
+        const multisig = anchor.web3.Keypair.generate();
+        await program.methods.createMultisig(owners.map((o) => o.publicKey), 2).accounts({
+            multisig: multisig.publicKey,
+            payer: provider.wallet.publicKey,
+        } as any).signers([multisig]).rpc();
+        const [multisigSigner] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("multisig_signer"), multisig.publicKey.toBuffer()],
+            program.programId
+        );
+
+        // Proposes `ix` as owners[0], optionally approves as owners[1], then executes.
+        const runThroughMultisig = async (ix: anchor.web3.TransactionInstruction, approve = true) => {
+            const multisigAccount = await program.account.multisig.fetch(multisig.publicKey);
+            const [transaction] = anchor.web3.PublicKey.findProgramAddressSync(
+                [Buffer.from("multisig_tx"), multisig.publicKey.toBuffer(), multisigAccount.nonce.toArrayLike(Buffer, "le", 8)],
+                program.programId
+            );
+            await program.methods.proposeMultisigTransaction(
+                ix.keys.map((k) => ({ pubkey: k.pubkey, isSigner: k.isSigner, isWritable: k.isWritable })),
+                ix.data
+            ).accounts({
+                multisig: multisig.publicKey,
+                transaction: transaction,
+                proposer: owners[0].publicKey,
+            } as any).signers([owners[0]]).rpc();
+            if (approve) {
+                await program.methods.approveMultisigTransaction().accounts({
+                    multisig: multisig.publicKey,
+                    transaction: transaction,
+                    approver: owners[1].publicKey,
+                } as any).signers([owners[1]]).rpc();
+            }
+            return program.methods.executeMultisigTransaction().accounts({
+                multisig: multisig.publicKey,
+                multisigSigner: multisigSigner,
+                transaction: transaction,
+            } as any).remainingAccounts([
+                ...ix.keys.map((k) => ({ pubkey: k.pubkey, isSigner: false, isWritable: k.isWritable })),
+                { pubkey: program.programId, isSigner: false, isWritable: false },
+            ]).rpc();
+        };
+
+        // Hand admin to the multisig signer PDA
+        await program.methods.proposeAdmin(multisigSigner).accounts({
+            globalState: globalState,
+            admin: provider.wallet.publicKey,
+        } as any).rpc();
+        const acceptIx = await program.methods.acceptAdmin().accounts({
+            globalState: globalState,
+            newAdmin: multisigSigner,
+        } as any).instruction();
+        await expectError("NotEnoughApprovals", runThroughMultisig(acceptIx, false));
+        await runThroughMultisig(acceptIx);
+        let state = await program.account.globalState.fetch(globalState);
+        assert.ok(state.admin.equals(multisigSigner));
+
+        // A single key can no longer act as admin
+        await expectError("Unauthorized", program.methods.togglePause(true).accounts({
+            globalState: globalState,
+            authority: provider.wallet.publicKey,
+        } as any).rpc());
+
+        // 2-of-3 can
+        const pauseIx = await program.methods.togglePause(true).accounts({
+            globalState: globalState,
+            authority: multisigSigner,
+        } as any).instruction();
+        await runThroughMultisig(pauseIx);
+        assert.ok((await program.account.globalState.fetch(globalState)).paused === true);
+        const unpauseIx = await program.methods.togglePause(false).accounts({
+            globalState: globalState,
+            authority: multisigSigner,
+        } as any).instruction();
+        await runThroughMultisig(unpauseIx);
+
+        // Hand admin back so later tests keep using the provider wallet
+        const proposeBackIx = await program.methods.proposeAdmin(provider.wallet.publicKey).accounts({
+            globalState: globalState,
+            admin: multisigSigner,
+        } as any).instruction();
+        await runThroughMultisig(proposeBackIx);
+        await program.methods.acceptAdmin().accounts({
+            globalState: globalState,
+            newAdmin: provider.wallet.publicKey,
+        } as any).rpc();
+        state = await program.account.globalState.fetch(globalState);
+        assert.ok(state.admin.equals(provider.wallet.publicKey));
+        assert.ok(state.paused === false);
+    });
+
+    it("PSM: Swaps USDC for USDT and back", async () => {
+        // 1. Create Mock USDC Mint
+        const usdcMint = await createMint(
+            provider.connection,
+            (provider.wallet as any).payer,
+            provider.wallet.publicKey,
+            null,
+            6
+        );
+
+        // 2. Configure PSM
+        const [psmConfig] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("psm"), usdcMint.toBuffer()],
+            program.programId
+        );
+        const [psmVault] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("psm_vault"), usdcMint.toBuffer()],
+            program.programId
+        );
+        const [psmFeeVault] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("psm_fee_vault"), usdcMint.toBuffer()],
+            program.programId
+        );
+
+        await setMockPrice(1);
+        await program.methods.configurePsm(legacyOracle, new anchor.BN(0), new anchor.BN(0)).accounts({
+            authority: provider.wallet.publicKey,
+            globalState: globalState,
+            tokenMint: usdcMint,
+            oracle: mockOracle,
+            psmConfig: psmConfig,
+            psmVault: psmVault,
+            usdtMint: mintPda,
+            psmFeeVault: psmFeeVault,
+        } as any).rpc();
+
+        // 3. Mint USDC to User
+        const userUsdcAccount = await createAssociatedTokenAccount(
+            provider.connection,
+            (provider.wallet as any).payer,
+            usdcMint,
+            userBody.publicKey
+        );
+        await mintTo(
+            provider.connection,
+            (provider.wallet as any).payer,
+            usdcMint,
+            userUsdcAccount,
+            provider.wallet.publicKey,
+            1000 * 1_000_000 // 1000 USDC
+        );
+
+        // 4. Swap USDC -> USDT
+        const userUsdtAccount = await anchor.utils.token.associatedAddress({
+            mint: mintPda,
+            owner: userBody.publicKey
+        });
+
+        await program.methods.swapUsdcToUsdt(new anchor.BN(500 * 1_000_000), new anchor.BN(0)).accounts({
+            user: userBody.publicKey,
+            psmConfig: psmConfig,
+            tokenMint: usdcMint,
+            oracle: mockOracle,
+            psmVault: psmVault,
+            userTokenAccount: userUsdcAccount,
+            usdtMint: mintPda,
+            userUsdtAccount: userUsdtAccount,
+            psmFeeVault: psmFeeVault,
+            globalState: globalState,
+        } as any).signers([userBody]).rpc();
+
+        // Check balances not easily possible without connection fetch, but if it didn't fail it's good.
+        const psmConfigAccount = await program.account.psmConfig.fetch(psmConfig);
         console.log("PSM Total Minted:", psmConfigAccount.totalMinted.toString());
         assert.ok(psmConfigAccount.totalMinted.eq(new anchor.BN(500 * 1_000_000)));
 
         // 5. Swap USDT -> USDC
-        await program.methods.swapUsdtToUsdc(new anchor.BN(500 * 1_000_000)).accounts({
+        await program.methods.swapUsdtToUsdc(new anchor.BN(500 * 1_000_000), new anchor.BN(0)).accounts({
             user: userBody.publicKey,
             psmConfig: psmConfig,
             tokenMint: usdcMint,
+            oracle: mockOracle,
             psmVault: psmVault,
             userTokenAccount: userUsdcAccount,
             usdtMint: mintPda,
             userUsdtAccount: userUsdtAccount,
+            psmFeeVault: psmFeeVault,
             globalState: globalState,
         } as any).signers([userBody]).rpc();
 
@@ -360,4 +1701,504 @@ describe("stablecoin_system", () => {
         console.log("PSM Total Minted After Redeem:", psmConfigAccountAfter.totalMinted.toString());
         assert.ok(psmConfigAccountAfter.totalMinted.eq(new anchor.BN(0)));
     });
+
+    it("PSM: Charges tin/tout into the fee vault", async () => {
+        const usdcMint = await createMint(
+            provider.connection,
+            (provider.wallet as any).payer,
+            provider.wallet.publicKey,
+            null,
+            6
+        );
+        const [psmConfig] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("psm"), usdcMint.toBuffer()],
+            program.programId
+        );
+        const [psmVault] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("psm_vault"), usdcMint.toBuffer()],
+            program.programId
+        );
+        const [psmFeeVault] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("psm_fee_vault"), usdcMint.toBuffer()],
+            program.programId
+        );
+
+        await setMockPrice(1);
+        await expectError("InvalidPsmFee", program.methods.configurePsm(legacyOracle, new anchor.BN(1001), new anchor.BN(0)).accounts({
+            authority: provider.wallet.publicKey,
+            globalState: globalState,
+            tokenMint: usdcMint,
+            oracle: mockOracle,
+            usdtMint: mintPda,
+        } as any).rpc());
+        await program.methods.configurePsm(legacyOracle, new anchor.BN(100), new anchor.BN(50)).accounts({
+            authority: provider.wallet.publicKey,
+            globalState: globalState,
+            tokenMint: usdcMint,
+            oracle: mockOracle,
+            usdtMint: mintPda,
+        } as any).rpc();
+
+        const swapper = anchor.web3.Keypair.generate();
+        const airdropTx = await provider.connection.requestAirdrop(swapper.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+        await provider.connection.confirmTransaction(airdropTx);
+        const swapperUsdc = await createAssociatedTokenAccount(
+            provider.connection,
+            (provider.wallet as any).payer,
+            usdcMint,
+            swapper.publicKey
+        );
+        await mintTo(
+            provider.connection,
+            (provider.wallet as any).payer,
+            usdcMint,
+            swapperUsdc,
+            provider.wallet.publicKey,
+            1000 * 1_000_000
+        );
+        const swapperUsdt = await anchor.utils.token.associatedAddress({ mint: mintPda, owner: swapper.publicKey });
+        const swapAccounts = {
+            user: swapper.publicKey,
+            psmConfig: psmConfig,
+            tokenMint: usdcMint,
+            oracle: mockOracle,
+            psmVault: psmVault,
+            userTokenAccount: swapperUsdc,
+            usdtMint: mintPda,
+            userUsdtAccount: swapperUsdt,
+            psmFeeVault: psmFeeVault,
+            globalState: globalState,
+        };
+
+        // tin: 1% of 1000 USDT goes to the fee vault, so 991 out is unreachable
+        await expectError("SlippageExceeded", program.methods.swapUsdcToUsdt(new anchor.BN(1000 * 1_000_000), new anchor.BN(991 * 1_000_000)).accounts(swapAccounts as any).signers([swapper]).rpc());
+        await program.methods.swapUsdcToUsdt(new anchor.BN(1000 * 1_000_000), new anchor.BN(990 * 1_000_000)).accounts(swapAccounts as any).signers([swapper]).rpc();
+        assert.equal((await getAccount(provider.connection, swapperUsdt)).amount.toString(), (990 * 1_000_000).toString());
+        assert.equal((await getAccount(provider.connection, psmFeeVault)).amount.toString(), (10 * 1_000_000).toString());
+
+        // tout: 0.5% of 200 USDT goes to the fee vault, the rest is redeemed
+        await expectError("SlippageExceeded", program.methods.swapUsdtToUsdc(new anchor.BN(200 * 1_000_000), new anchor.BN(200 * 1_000_000)).accounts(swapAccounts as any).signers([swapper]).rpc());
+        await program.methods.swapUsdtToUsdc(new anchor.BN(200 * 1_000_000), new anchor.BN(199 * 1_000_000)).accounts(swapAccounts as any).signers([swapper]).rpc();
+        assert.equal((await getAccount(provider.connection, swapperUsdc)).amount.toString(), (199 * 1_000_000).toString());
+        assert.equal((await getAccount(provider.connection, psmFeeVault)).amount.toString(), (11 * 1_000_000).toString());
+
+        // Anyone can sweep fees into the treasury, which sends 20% to its fee recipient and keeps the rest as surplus
+        const recipient = await createAssociatedTokenAccount(
+            provider.connection,
+            (provider.wallet as any).payer,
+            mintPda,
+            anchor.web3.Keypair.generate().publicKey
+        );
+        const treasuryParams = (surplusBps: number, feeRecipientBps: number) => ({
+            surplusBps: new anchor.BN(surplusBps),
+            liquidatorBps: new anchor.BN(0),
+            feeRecipientBps: new anchor.BN(feeRecipientBps),
+            bufferFloor: new anchor.BN(0),
+            windowSecs: new anchor.BN(86_400),
+            windowLimit: new anchor.BN(0),
+        });
+        await program.methods.configureTreasury(treasuryParams(8_000, 2_000)).accounts({
+            feeRecipient: recipient,
+            admin: provider.wallet.publicKey,
+        } as any).rpc();
+        await new Promise((resolve) => setTimeout(resolve, 2000));
+        await program.methods.applyTreasuryParams().accounts({} as any).rpc();
+        const [surplus] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("surplus")], program.programId);
+        const surplusBefore = (await getAccount(provider.connection, surplus)).amount;
+        await expectError("MissingFeeRecipient", program.methods.collectPsmFees().accounts({
+            payer: swapper.publicKey,
+            psmConfig: psmConfig,
+            psmFeeVault: psmFeeVault,
+            feeRecipientAccount: null,
+        } as any).signers([swapper]).rpc());
+        await program.methods.collectPsmFees().accounts({
+            payer: swapper.publicKey,
+            psmConfig: psmConfig,
+            psmFeeVault: psmFeeVault,
+            feeRecipientAccount: recipient,
+        } as any).signers([swapper]).rpc();
+        assert.equal((await getAccount(provider.connection, psmFeeVault)).amount.toString(), "0");
+        assert.equal((await getAccount(provider.connection, recipient)).amount, BigInt(2_200_000));
+        assert.equal((await getAccount(provider.connection, surplus)).amount - surplusBefore, BigInt(8_800_000));
+        const treasury = await program.account.treasury.fetch(treasuryPda);
+        assert.ok(treasury.totalPsmFees.gte(new anchor.BN(11 * 1_000_000)));
+
+        await program.methods.configureTreasury(treasuryParams(10_000, 0)).accounts({
+            feeRecipient: null,
+            admin: provider.wallet.publicKey,
+        } as any).rpc();
+    });
+
+    it("PSM: Enforces mint cap, rate limit and price band", async () => {
+        const usdcMint = await createMint(
+            provider.connection,
+            (provider.wallet as any).payer,
+            provider.wallet.publicKey,
+            null,
+            6
+        );
+        const [psmConfig] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("psm"), usdcMint.toBuffer()],
+            program.programId
+        );
+        const [psmFeeVault] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from("psm_fee_vault"), usdcMint.toBuffer()],
+            program.programId
+        );
+
+        await setMockPrice(1);
+        await program.methods.configurePsm(legacyOracle, new anchor.BN(0), new anchor.BN(0)).accounts({
+            authority: provider.wallet.publicKey,
+            globalState: globalState,
+            tokenMint: usdcMint,
+            oracle: mockOracle,
+            usdtMint: mintPda,
+        } as any).rpc();
+
+        const setLimits = (cap: number, windowLimit: number, authority?: anchor.web3.Keypair) => {
+            const call = program.methods.setPsmLimits(
+                new anchor.BN(cap * 1_000_000),
+                new anchor.BN(3600),
+                new anchor.BN(windowLimit * 1_000_000),
+                new anchor.BN(990_000),
+                new anchor.BN(1_010_000)
+            ).accounts({
+                psmConfig: psmConfig,
+                globalState: globalState,
+                authority: authority ? authority.publicKey : provider.wallet.publicKey,
+            } as any);
+            return authority ? call.signers([authority]).rpc() : call.rpc();
+        };
+        const outsider = anchor.web3.Keypair.generate();
+        const applyPsmParams = () => retryWhile("TimelockNotElapsed", () => program.methods.applyPsmParams().accounts({
+            psmConfig: psmConfig,
+        } as any).rpc());
+        await expectError("Unauthorized", setLimits(1000, 600, outsider));
+        // A shorter window refills sooner, so the new limits wait out the timelock
+        await setLimits(1000, 600);
+        assert.ok((await program.account.psmConfig.fetch(psmConfig)).pendingParamsEta.gtn(0));
+        await applyPsmParams();
+
+        const swapper = anchor.web3.Keypair.generate();
+        const airdropTx = await provider.connection.requestAirdrop(swapper.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+        await provider.connection.confirmTransaction(airdropTx);
+        const swapperUsdc = await createAssociatedTokenAccount(
+            provider.connection,
+            (provider.wallet as any).payer,
+            usdcMint,
+            swapper.publicKey
+        );
+        await mintTo(
+            provider.connection,
+            (provider.wallet as any).payer,
+            usdcMint,
+            swapperUsdc,
+            provider.wallet.publicKey,
+            2000 * 1_000_000
+        );
+        const swapperUsdt = await anchor.utils.token.associatedAddress({ mint: mintPda, owner: swapper.publicKey });
+        const swapAccounts = {
+            user: swapper.publicKey,
+            psmConfig: psmConfig,
+            tokenMint: usdcMint,
+            oracle: mockOracle,
+            userTokenAccount: swapperUsdc,
+            usdtMint: mintPda,
+            userUsdtAccount: swapperUsdt,
+            psmFeeVault: psmFeeVault,
+            globalState: globalState,
+        };
+        const swapIn = (amount: number) => program.methods.swapUsdcToUsdt(new anchor.BN(amount * 1_000_000), new anchor.BN(0)).accounts(swapAccounts as any).signers([swapper]).rpc();
+        const swapOut = (amount: number) => program.methods.swapUsdtToUsdc(new anchor.BN(amount * 1_000_000), new anchor.BN(0)).accounts(swapAccounts as any).signers([swapper]).rpc();
+
+        // 500 + 200 exceeds the 600 per-window limit
+        await swapIn(500);
+        await expectError("PsmRateLimitExceeded", swapIn(200));
+
+        // With the window limit lifted, 500 + 600 exceeds the 1000 hard cap
+        await setLimits(1000, 10_000);
+        await applyPsmParams();
+        await expectError("PsmMintCapExceeded", swapIn(600));
+        await swapIn(500);
+
+        // Redemptions are windowed separately
+        await setLimits(1000, 300);
+        await swapOut(300);
+        await expectError("PsmRateLimitExceeded", swapOut(1));
+
+        // Minting refuses a depegged backing price
+        await setMockPrice(0.5);
+        await expectError("PsmPriceOutOfBand", swapIn(1));
+        await setMockPrice(1);
+
+        const config = await program.account.psmConfig.fetch(psmConfig);
+        assert.ok(config.totalMinted.eq(new anchor.BN(700 * 1_000_000)));
+    });
+
+    it("PSM: Normalizes 6-, 8- and 9-decimal backing", async () => {
+        await setMockPrice(1);
+        for (const decimals of [6, 8, 9]) {
+            const backingMint = await createMint(
+                provider.connection,
+                (provider.wallet as any).payer,
+                provider.wallet.publicKey,
+                null,
+                decimals
+            );
+            const [psmConfig] = anchor.web3.PublicKey.findProgramAddressSync(
+                [Buffer.from("psm"), backingMint.toBuffer()],
+                program.programId
+            );
+            const [psmVault] = anchor.web3.PublicKey.findProgramAddressSync(
+                [Buffer.from("psm_vault"), backingMint.toBuffer()],
+                program.programId
+            );
+            const [psmFeeVault] = anchor.web3.PublicKey.findProgramAddressSync(
+                [Buffer.from("psm_fee_vault"), backingMint.toBuffer()],
+                program.programId
+            );
+            await program.methods.configurePsm(legacyOracle, new anchor.BN(0), new anchor.BN(0)).accounts({
+                authority: provider.wallet.publicKey,
+                globalState: globalState,
+                tokenMint: backingMint,
+                oracle: mockOracle,
+                usdtMint: mintPda,
+            } as any).rpc();
+
+            const swapper = anchor.web3.Keypair.generate();
+            const airdropTx = await provider.connection.requestAirdrop(swapper.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+            await provider.connection.confirmTransaction(airdropTx);
+            const swapperBacking = await createAssociatedTokenAccount(
+                provider.connection,
+                (provider.wallet as any).payer,
+                backingMint,
+                swapper.publicKey
+            );
+            const unit = BigInt(10) ** BigInt(decimals);
+            await mintTo(
+                provider.connection,
+                (provider.wallet as any).payer,
+                backingMint,
+                swapperBacking,
+                provider.wallet.publicKey,
+                BigInt(100) * unit
+            );
+            const swapperUsdt = await anchor.utils.token.associatedAddress({ mint: mintPda, owner: swapper.publicKey });
+            const swapAccounts = {
+                user: swapper.publicKey,
+                psmConfig: psmConfig,
+                tokenMint: backingMint,
+                oracle: mockOracle,
+                userTokenAccount: swapperBacking,
+                usdtMint: mintPda,
+                userUsdtAccount: swapperUsdt,
+                psmFeeVault: psmFeeVault,
+                globalState: globalState,
+            };
+
+            // 100 whole backing tokens mint 100 USDT regardless of decimals
+            await program.methods.swapUsdcToUsdt(new anchor.BN((BigInt(100) * unit).toString()), new anchor.BN(0)).accounts(swapAccounts as any).signers([swapper]).rpc();
+            assert.equal((await getAccount(provider.connection, swapperUsdt)).amount.toString(), (100 * 1_000_000).toString());
+
+            // 40 USDT redeems 40 whole backing tokens
+            await program.methods.swapUsdtToUsdc(new anchor.BN(40 * 1_000_000), new anchor.BN(0)).accounts(swapAccounts as any).signers([swapper]).rpc();
+            assert.equal((await getAccount(provider.connection, swapperBacking)).amount.toString(), (BigInt(40) * unit).toString());
+
+            const config = await program.account.psmConfig.fetch(psmConfig);
+            assert.ok(config.totalMinted.eq(new anchor.BN(60 * 1_000_000)));
+            assert.equal(config.reserves.toString(), (BigInt(60) * unit).toString());
+            assert.equal((await getAccount(provider.connection, psmVault)).amount.toString(), config.reserves.toString());
+        }
+    });
+
+    it("Keeps tracked supply in line with the USDT mint", async () => {
+        const state = await program.account.globalState.fetch(globalState);
+        const mintSupply = await provider.connection.getTokenSupply(mintPda);
+        console.log("Total Supply:", state.totalSupply.toString(), "CDP:", state.cdpSupply.toString(), "PSM:", state.psmSupply.toString());
+        assert.ok(state.totalSupply.eq(new anchor.BN(mintSupply.value.amount)));
+        assert.ok(state.cdpSupply.add(state.psmSupply).eq(state.totalSupply));
+
+        await program.methods.checkSolvency().accounts({
+            globalState: globalState,
+            usdtMint: mintPda,
+        } as any).rpc();
+    });
+
+    // Global settlement is one-way, so this must stay the last test.
+    it("Global settlement: closes positions at frozen prices and redeems USDT pro-rata", async () => {
+        // A PSM holding 100 USDC of reserves
+        await setMockPrice(1);
+        const usdcMint = await createMint(provider.connection, (provider.wallet as any).payer, provider.wallet.publicKey, null, 6);
+        const [psmConfig] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("psm"), usdcMint.toBuffer()], program.programId);
+        const [psmVault] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("psm_vault"), usdcMint.toBuffer()], program.programId);
+        const [psmFeeVault] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("psm_fee_vault"), usdcMint.toBuffer()], program.programId);
+        await program.methods.configurePsm(legacyOracle, new anchor.BN(0), new anchor.BN(0)).accounts({
+            authority: provider.wallet.publicKey,
+            globalState: globalState,
+            tokenMint: usdcMint,
+            oracle: mockOracle,
+            usdtMint: mintPda,
+        } as any).rpc();
+        const swapper = anchor.web3.Keypair.generate();
+        await provider.connection.confirmTransaction(await provider.connection.requestAirdrop(swapper.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL));
+        const swapperUsdc = await createAssociatedTokenAccount(provider.connection, (provider.wallet as any).payer, usdcMint, swapper.publicKey);
+        await mintTo(provider.connection, (provider.wallet as any).payer, usdcMint, swapperUsdc, provider.wallet.publicKey, 100 * 1_000_000);
+        await program.methods.swapUsdcToUsdt(new anchor.BN(100 * 1_000_000), new anchor.BN(0)).accounts({
+            user: swapper.publicKey,
+            psmConfig: psmConfig,
+            tokenMint: usdcMint,
+            oracle: mockOracle,
+            psmVault: psmVault,
+            userTokenAccount: swapperUsdc,
+            usdtMint: mintPda,
+            userUsdtAccount: await anchor.utils.token.associatedAddress({ mint: mintPda, owner: swapper.publicKey }),
+            psmFeeVault: psmFeeVault,
+            globalState: globalState,
+        } as any).signers([swapper]).rpc();
+
+        // Two positions of 10 tokens @ $100, one with 500 and one with 600 debt
+        await setMockPrice(100);
+        const { mint, config } = await listCollateral(150, 120, 10);
+        const owner = anchor.web3.Keypair.generate();
+        const { position, tokenAccount } = await openPosition(owner, mint, 10, 500);
+        const other = anchor.web3.Keypair.generate();
+        const { position: otherPosition, tokenAccount: otherTokenAccount } = await openPosition(other, mint, 10, 600);
+
+        const globalSettle = (waitSecs: number, signer?: anchor.web3.Keypair) => {
+            const builder = program.methods.globalSettle(new anchor.BN(waitSecs)).accounts({
+                admin: (signer ?? (provider.wallet as any).payer).publicKey,
+            } as any);
+            return signer ? builder.signers([signer]).rpc() : builder.rpc();
+        };
+        await expectError("Unauthorized", globalSettle(2, owner));
+        await expectError("InvalidSettlementWait", globalSettle(-1));
+        await globalSettle(2);
+        await expectError("SystemSettled", program.methods.togglePause(false).accounts({
+            globalState: globalState,
+            authority: provider.wallet.publicKey,
+        } as any).rpc());
+        await expectError("SystemSettled", program.methods.setStabilityFee(new anchor.BN(100)).accounts({
+            collateralConfig: config,
+            authority: provider.wallet.publicKey,
+        } as any).rpc());
+        // The cached price can't move once settlement has started
+        await expectError("SystemSettled", program.methods.poke().accounts({
+            priceCache: anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("price_cache"), mint.toBuffer()], program.programId)[0],
+            collateralConfig: config,
+            oracle: mockOracle,
+        } as any).rpc());
+        await expectError("SystemSettled", program.methods.setDebtCeiling(new anchor.BN(0)).accounts({
+            collateralConfig: config,
+            authority: provider.wallet.publicKey,
+        } as any).rpc());
+        await expectError("SystemSettled", program.methods.setPsmLimits(
+            new anchor.BN(0),
+            new anchor.BN(3600),
+            new anchor.BN(0),
+            new anchor.BN(0),
+            new anchor.BN(0)
+        ).accounts({
+            psmConfig: psmConfig,
+            authority: provider.wallet.publicKey,
+        } as any).rpc());
+
+        const settlePosition = (positionOwner: anchor.web3.PublicKey) => program.methods.settlePosition().accounts({
+            payer: provider.wallet.publicKey,
+            owner: positionOwner,
+            collateralMint: mint,
+        } as any).rpc();
+        await expectError("CollateralNotSettled", settlePosition(owner.publicKey));
+        await program.methods.settleCollateral().accounts({ collateralConfig: config } as any).rpc();
+        await expectError("CollateralAlreadySettled", program.methods.settleCollateral().accounts({ collateralConfig: config } as any).rpc());
+
+        // 500 debt @ $100 takes 5 tokens; the other 5 go back to the owner
+        await settlePosition(owner.publicKey);
+        const positionAccount = await program.account.position.fetch(position);
+        assert.ok(positionAccount.debtAmount.eqn(0));
+        assert.ok(positionAccount.collateralAmount.eqn(0));
+        assert.equal((await getAccount(provider.connection, tokenAccount)).amount, BigInt(5_000_000_000));
+
+        // The supply is fixed once the wait is over
+        await expectError("SettlementWaitNotElapsed", program.methods.fixSettlement().accounts({} as any).rpc());
+        await new Promise((resolve) => setTimeout(resolve, 2_500));
+        // Surplus, the backstop and PSM fee vaults are protocol-owned and never redeem
+        await expectError("SystemSettled", program.methods.collectPsmFees().accounts({
+            payer: provider.wallet.publicKey,
+            psmConfig: psmConfig,
+            psmFeeVault: psmFeeVault,
+            feeRecipientAccount: null,
+        } as any).rpc());
+        // Every PSM is passed as a (config, fee vault) pair, and none may be left out
+        const readonly = (pubkey: anchor.web3.PublicKey) => ({ pubkey, isSigner: false, isWritable: false });
+        const fixSettlement = (pairs: [anchor.web3.PublicKey, anchor.web3.PublicKey][]) => program.methods.fixSettlement().accounts({} as any)
+            .remainingAccounts(pairs.flatMap(([psm, feeVault]) => [readonly(psm), readonly(feeVault)])).rpc();
+        const allPsms = (await program.account.psmConfig.all()).map((p): [anchor.web3.PublicKey, anchor.web3.PublicKey] => [p.publicKey, p.account.feeVault]);
+        assert.equal((await program.account.globalState.fetch(globalState)).psmCount, allPsms.length);
+        await expectError("InvalidSettlementAccount", fixSettlement([[psmConfig, tokenAccount]]));
+        await expectError("MissingPsmFeeVault", fixSettlement([[psmConfig, psmFeeVault]]));
+        await fixSettlement(allPsms);
+        const [settlementPda] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("settlement")], program.programId);
+        const supply = BigInt((await program.account.settlement.fetch(settlementPda)).redeemableSupply.toString());
+
+        // Only the 5 tokens taken from the settled position so far
+        const [vault] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("vault"), mint.toBuffer()], program.programId);
+        await program.methods.openCollateralPool().accounts({ payer: provider.wallet.publicKey, collateralMint: mint } as any).rpc();
+        await program.methods.openPsmPool().accounts({ payer: provider.wallet.publicKey, psmConfig: psmConfig, psmVault: psmVault } as any).rpc();
+        const [collateralPool] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("settlement_pool"), vault.toBuffer()], program.programId);
+        const [psmPool] = anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("settlement_pool"), psmVault.toBuffer()], program.programId);
+        assert.ok((await program.account.settlementPool.fetch(collateralPool)).amount.eq(new anchor.BN(5_000_000_000)));
+        assert.ok((await program.account.settlementPool.fetch(psmPool)).amount.eq(new anchor.BN(100 * 1_000_000)));
+
+        // The owner redeems their 500 USDT for a pro-rata share of each pool
+        await program.methods.redeem(new anchor.BN(500 * 1_000_000)).accounts({ holder: owner.publicKey } as any).signers([owner]).rpc();
+        const cash = (pool: anchor.web3.PublicKey, poolVault: anchor.web3.PublicKey, poolMint: anchor.web3.PublicKey, poolConfig: anchor.web3.PublicKey | null) => program.methods.cash().accounts({
+            holder: owner.publicKey,
+            pool: pool,
+            collateralConfig: poolConfig,
+            vault: poolVault,
+            mint: poolMint,
+        } as any).signers([owner]).rpc();
+        await expectError("InvalidSettlementAccount", cash(collateralPool, vault, mint, null));
+        await cash(collateralPool, vault, mint, config);
+        await cash(psmPool, psmVault, usdcMint, null);
+        assert.equal(
+            (await getAccount(provider.connection, tokenAccount)).amount,
+            BigInt(5_000_000_000) + BigInt(5_000_000_000) * BigInt(500 * 1_000_000) / supply
+        );
+
+        // Settling the other position (600 debt @ $100 = 6 tokens) grows the pool for everyone,
+        // even while it is frozen; its excess 4 tokens wait until it is unfrozen
+        const freeze = (frozen: boolean) => program.methods.toggleFreeze(frozen).accounts({
+            globalState: globalState,
+            position: otherPosition,
+            authority: provider.wallet.publicKey,
+        } as any).rpc();
+        await freeze(true);
+        await settlePosition(other.publicKey);
+        let otherAccount = await program.account.position.fetch(otherPosition);
+        assert.ok(otherAccount.debtAmount.eqn(0));
+        assert.ok(otherAccount.collateralAmount.eq(new anchor.BN(4_000_000_000)));
+        assert.equal((await getAccount(provider.connection, otherTokenAccount)).amount, BigInt(0));
+        await freeze(false);
+        await settlePosition(other.publicKey);
+        otherAccount = await program.account.position.fetch(otherPosition);
+        assert.ok(otherAccount.collateralAmount.eqn(0));
+        assert.equal((await getAccount(provider.connection, otherTokenAccount)).amount, BigInt(4_000_000_000));
+        await cash(collateralPool, vault, mint, config);
+        assert.ok((await program.account.settlementPool.fetch(collateralPool)).amount.eq(new anchor.BN(11_000_000_000)));
+        const collateralShare = BigInt(11_000_000_000) * BigInt(500 * 1_000_000) / supply;
+        const usdcShare = BigInt(100 * 1_000_000) * BigInt(500 * 1_000_000) / supply;
+        assert.equal((await getAccount(provider.connection, tokenAccount)).amount, BigInt(5_000_000_000) + collateralShare);
+        const ownerUsdc = await anchor.utils.token.associatedAddress({ mint: usdcMint, owner: owner.publicKey });
+        assert.equal((await getAccount(provider.connection, ownerUsdc)).amount, usdcShare);
+
+        // Cashing again pays nothing new
+        await cash(collateralPool, vault, mint, config);
+        assert.equal((await getAccount(provider.connection, tokenAccount)).amount, BigInt(5_000_000_000) + collateralShare);
+
+        await program.methods.checkSolvency().accounts({
+            globalState: globalState,
+            usdtMint: mintPda,
+        } as any).rpc();
+    });
 });